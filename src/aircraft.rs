@@ -0,0 +1,179 @@
+use crate::encoder::Encode;
+use crate::parser::Parse;
+use regex::Regex;
+
+/// Aircraft type as encoded in bits 2-5 of the first byte of the OGN id
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AircraftType {
+    Unknown = 0x0,
+    Glider = 0x1,
+    TowPlane = 0x2,
+    Helicopter = 0x3,
+    Parachute = 0x4,
+    DropPlane = 0x5,
+    HangGlider = 0x6,
+    Paraglider = 0x7,
+    PoweredAircraft = 0x8,
+    JetAircraft = 0x9,
+    Ufo = 0xA,
+    Balloon = 0xB,
+    Airship = 0xC,
+    Uav = 0xD,
+    GroundSupport = 0xE,
+    StaticObject = 0xF,
+}
+
+impl AircraftType {
+    pub fn from_u8(value: u8) -> Self {
+        match value & 0x0F {
+            0x1 => AircraftType::Glider,
+            0x2 => AircraftType::TowPlane,
+            0x3 => AircraftType::Helicopter,
+            0x4 => AircraftType::Parachute,
+            0x5 => AircraftType::DropPlane,
+            0x6 => AircraftType::HangGlider,
+            0x7 => AircraftType::Paraglider,
+            0x8 => AircraftType::PoweredAircraft,
+            0x9 => AircraftType::JetAircraft,
+            0xA => AircraftType::Ufo,
+            0xB => AircraftType::Balloon,
+            0xC => AircraftType::Airship,
+            0xD => AircraftType::Uav,
+            0xE => AircraftType::GroundSupport,
+            0xF => AircraftType::StaticObject,
+            _ => AircraftType::Unknown,
+        }
+    }
+
+    /// APRS symbol table and code used by OGN for the aircraft type
+    pub fn symbol(&self) -> (char, char) {
+        match self {
+            AircraftType::Unknown => ('/', 'z'),
+            AircraftType::Glider => ('/', '\''), // (moto-)glider (most frequent)
+            AircraftType::TowPlane => ('/', '\''),
+            AircraftType::Helicopter => ('/', 'X'),
+            AircraftType::Parachute => ('/', 'g'), // often mixed with drop plane
+            AircraftType::DropPlane => ('\\', '^'),
+            AircraftType::HangGlider => ('/', 'g'),
+            AircraftType::Paraglider => ('/', 'g'),
+            AircraftType::PoweredAircraft => ('\\', '^'),
+            AircraftType::JetAircraft => ('/', '^'),
+            AircraftType::Ufo => ('/', 'z'), // people set for fun
+            AircraftType::Balloon => ('/', 'O'),
+            AircraftType::Airship => ('/', 'O'),
+            AircraftType::Uav => ('/', '\''),
+            AircraftType::GroundSupport => ('/', 'z'), // ground vehicles at airfields
+            AircraftType::StaticObject => ('\\', 'n'),
+        }
+    }
+}
+
+/// Address type as encoded in bits 0-1 of the first byte of the OGN id
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AddressType {
+    Random = 0,
+    Icao = 1,
+    Flarm = 2,
+    Ogn = 3,
+}
+
+/// The OGN id `STttttaaAAAAAA` of the `idXXXXXXXX` field: stealth and no-tracking flags, aircraft
+/// type, address type and the 24 bit address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OgnId {
+    pub address: u32,
+    pub address_type: AddressType,
+    pub aircraft_type: AircraftType,
+    pub stealth: bool,
+    pub no_tracking: bool,
+}
+
+impl OgnId {
+    pub fn new(address: u32, address_type: AddressType, aircraft_type: AircraftType) -> Self {
+        Self {
+            address: address & 0x00FF_FFFF,
+            address_type,
+            aircraft_type,
+            stealth: false,
+            no_tracking: false,
+        }
+    }
+}
+
+impl Parse for OgnId {
+    type Item = Self;
+
+    /// Parses the 8 hex digits following `id`, e.g. `0ADDE626`
+    fn parse(id: &str) -> Option<Self> {
+        let regex =
+            Regex::new(r"^(?:id)?(?P<flags>[0-9A-Fa-f]{2})(?P<address>[0-9A-Fa-f]{6})$").unwrap();
+        let captures = regex.captures(id)?;
+
+        let flags = u8::from_str_radix(captures.name("flags").unwrap().as_str(), 16).ok()?;
+        let address = u32::from_str_radix(captures.name("address").unwrap().as_str(), 16).ok()?;
+
+        Some(Self {
+            address,
+            address_type: match flags & 0x03 {
+                0 => AddressType::Random,
+                1 => AddressType::Icao,
+                2 => AddressType::Flarm,
+                _ => AddressType::Ogn,
+            },
+            aircraft_type: AircraftType::from_u8(flags >> 2),
+            stealth: flags & 0x80 != 0,
+            no_tracking: flags & 0x40 != 0,
+        })
+    }
+}
+
+impl Encode for OgnId {
+    /// Renders the 8 hex digits without the `id` prefix
+    fn to_aprs_string(&self) -> String {
+        let flags = (self.stealth as u8) << 7
+            | (self.no_tracking as u8) << 6
+            | (self.aircraft_type as u8) << 2
+            | self.address_type as u8;
+
+        format!("{:02X}{:06X}", flags, self.address & 0x00FF_FFFF)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_ogn_id_test() {
+        let id = OgnId::parse("0ADDE626").unwrap();
+        assert_eq!(
+            id,
+            OgnId::new(0xDDE626, AddressType::Flarm, AircraftType::TowPlane)
+        );
+        assert_eq!(id.to_aprs_string(), "0ADDE626");
+
+        let id = OgnId::parse("id3782149C").unwrap();
+        assert_eq!(id.aircraft_type, AircraftType::Uav);
+        assert_eq!(id.address_type, AddressType::Ogn);
+        assert_eq!(id.address, 0x82149C);
+        assert!(!id.stealth && !id.no_tracking);
+
+        let id = OgnId::parse("C6123456").unwrap();
+        assert!(id.stealth && id.no_tracking);
+        assert_eq!(id.aircraft_type, AircraftType::Glider);
+        assert_eq!(id.to_aprs_string(), "C6123456");
+
+        assert_eq!(OgnId::parse("3782149"), None);
+        assert_eq!(OgnId::parse("3782149G"), None);
+    }
+
+    #[test]
+    fn aircraft_symbol_test() {
+        assert_eq!(AircraftType::Glider.symbol(), ('/', '\''));
+        assert_eq!(AircraftType::from_u8(0x8).symbol(), ('\\', '^'));
+        assert_eq!(AircraftType::from_u8(0xF), AircraftType::StaticObject);
+    }
+}
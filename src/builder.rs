@@ -0,0 +1,409 @@
+//! Builder collecting the configuration of an `APRSClient`
+
+use crate::connection::{RetryPolicy, Timeouts};
+use crate::event::{ConnectionEvent, EventCallback};
+use crate::rate_limit::TokenBucket;
+use crate::transport::TcpTransport;
+use crate::{APRSClient, LineDecoding, LoginData, ReceivedMessage, MAX_LINE_LENGTH, PORT};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+type Callback = Box<dyn Fn(&ReceivedMessage) + Send>;
+
+/// Configuration of an `APRSClient`, validated by `build`. The built client is not connected yet,
+/// `reconnect` (or `run`) connects, logs in and applies the filter.
+///
+/// ```no_run
+/// use ogn_client_rs::{APRSClient, LoginData, PORT};
+///
+/// let client = APRSClient::builder()
+///     .servers(&["glidern1.glidernet.org", "glidern2.glidernet.org"])
+///     .port(PORT::FILTER)
+///     .login(LoginData::new().user_name("N0CALL").pass_code("13023"))
+///     .filter("r/47/7/100")
+///     .callback(|message| println!("{}", message.message))
+///     .build()
+///     .unwrap();
+///
+/// APRSClient::run(client.clone());
+/// ```
+pub struct AprsClientBuilder {
+    servers: Vec<String>,
+    port: PORT,
+    login: Option<LoginData>,
+    filter: Option<String>,
+    callbacks: Vec<Callback>,
+    event_callback: Option<EventCallback>,
+    timeouts: Timeouts,
+    retry_policy: RetryPolicy,
+    keepalive_interval: Option<Duration>,
+    rate_limit: Option<TokenBucket>,
+    max_line_length: usize,
+    line_decoding: LineDecoding,
+    #[cfg(feature = "tls")]
+    tls: Option<crate::connection::TlsConfig>,
+}
+
+impl AprsClientBuilder {
+    /// Starts with no server on the filter port
+    pub fn new() -> Self {
+        Self {
+            servers: Vec::new(),
+            port: PORT::FILTER,
+            login: None,
+            filter: None,
+            callbacks: Vec::new(),
+            event_callback: None,
+            timeouts: Timeouts::default(),
+            retry_policy: RetryPolicy::default(),
+            keepalive_interval: None,
+            rate_limit: Some(TokenBucket::default()),
+            max_line_length: MAX_LINE_LENGTH,
+            line_decoding: LineDecoding::default(),
+            #[cfg(feature = "tls")]
+            tls: None,
+        }
+    }
+
+    /// Adds a server given as `host` or `host:port`, the servers are tried in the given order
+    pub fn server(mut self, server: &str) -> Self {
+        self.servers.push(server.to_string());
+        self
+    }
+
+    pub fn servers(mut self, servers: &[&str]) -> Self {
+        self.servers
+            .extend(servers.iter().map(|server| server.to_string()));
+        self
+    }
+
+    /// Port of the servers given without port
+    pub fn port(mut self, port: PORT) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// Logs in after every connect
+    pub fn login(mut self, login_data: LoginData) -> Self {
+        self.login = Some(login_data);
+        self
+    }
+
+    /// Filter applied after every login (verified or not), requires a port supporting filters
+    pub fn filter(mut self, filter_expression: &str) -> Self {
+        self.filter = Some(filter_expression.to_string());
+        self
+    }
+
+    /// Adds a callback, all callbacks are called with every received line in the order they
+    /// were added
+    pub fn callback(mut self, callback: impl Fn(&ReceivedMessage) + Send + 'static) -> Self {
+        self.callbacks.push(Box::new(callback));
+        self
+    }
+
+    /// Callback notified about changes of the connection state
+    pub fn event_callback(
+        mut self,
+        event_callback: impl Fn(&ConnectionEvent) + Send + 'static,
+    ) -> Self {
+        self.event_callback = Some(Box::new(event_callback));
+        self
+    }
+
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Delays between the reconnect attempts of the `run` loop
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Interval of the `#keepalive` comments the `run` loop sends while no other line is sent
+    pub fn keepalive_interval(mut self, keepalive_interval: Duration) -> Self {
+        self.keepalive_interval = Some(keepalive_interval);
+        self
+    }
+
+    /// Limits the rate of positions and status messages sent to the server, `None` disables the
+    /// limit
+    pub fn rate_limit(mut self, rate_limit: Option<TokenBucket>) -> Self {
+        self.rate_limit = rate_limit;
+        self
+    }
+
+    /// Longer lines are skipped (default: `MAX_LINE_LENGTH`)
+    pub fn max_line_length(mut self, max_line_length: usize) -> Self {
+        self.max_line_length = max_line_length;
+        self
+    }
+
+    pub fn line_decoding(mut self, line_decoding: LineDecoding) -> Self {
+        self.line_decoding = line_decoding;
+        self
+    }
+
+    /// Connects via TLS, see `TlsConfig`
+    #[cfg(feature = "tls")]
+    pub fn tls(mut self, tls: crate::connection::TlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Validates the configuration and creates the client without connecting
+    pub fn build(self) -> Result<Arc<Mutex<APRSClient>>, std::io::Error> {
+        self.validate()?;
+
+        let servers: Vec<&str> = self.servers.iter().map(String::as_str).collect();
+        let transport =
+            TcpTransport::with_servers(&servers, self.port.number()).timeouts(self.timeouts);
+        #[cfg(feature = "tls")]
+        let transport = match self.tls {
+            Some(tls) => transport.tls(tls),
+            None => transport,
+        };
+
+        let callbacks = self.callbacks;
+        let callback = Box::new(move |message: &ReceivedMessage| {
+            for callback in &callbacks {
+                callback(message);
+            }
+        });
+
+        let client = APRSClient::with_transport(transport, self.port, callback);
+        {
+            let mut locked_client = client.lock().unwrap();
+            locked_client.set_retry_policy(self.retry_policy)?;
+            locked_client.set_keepalive_interval(self.keepalive_interval);
+            locked_client.set_rate_limit(self.rate_limit);
+            locked_client.set_max_line_length(self.max_line_length);
+            locked_client.set_line_decoding(self.line_decoding);
+            if let Some(event_callback) = self.event_callback {
+                locked_client.set_event_callback(event_callback);
+            }
+            if let Some(login_data) = self.login {
+                locked_client.set_login_data(login_data);
+            }
+            if let Some(filter) = &self.filter {
+                locked_client.set_filter_expression(filter);
+            }
+        }
+
+        Ok(client)
+    }
+
+    fn validate(&self) -> Result<(), std::io::Error> {
+        let invalid = |message: &str| {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                message.to_string(),
+            ))
+        };
+
+        if self.servers.is_empty() {
+            return invalid("no server given");
+        }
+        if self.servers.iter().any(|server| server.trim().is_empty()) {
+            return invalid("empty server name");
+        }
+
+        if let Some(login_data) = &self.login {
+            if login_data.user_name.is_empty() {
+                return invalid("empty user name");
+            }
+        }
+
+        if let Some(filter) = &self.filter {
+            if !self.port.capabilities().filter {
+                return invalid("the port does not support filters");
+            }
+            if filter.trim().is_empty() {
+                return invalid("empty filter");
+            }
+            if self.login.is_none() {
+                return invalid("the server applies filters only after a login");
+            }
+        }
+
+        if self.max_line_length == 0 {
+            return invalid("the maximum line length must not be zero");
+        }
+
+        let zero = Duration::ZERO;
+        if self.timeouts.connect == zero
+            || self.timeouts.read == Some(zero)
+            || self.timeouts.write == Some(zero)
+        {
+            return invalid("timeouts must not be zero");
+        }
+        if self.keepalive_interval == Some(zero) {
+            return invalid("the keepalive interval must not be zero");
+        }
+
+        self.retry_policy.validate()
+    }
+}
+
+impl Default for AprsClientBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::MockServer;
+
+    fn login_data() -> LoginData {
+        LoginData::new().user_name("N0CALL").pass_code("13023")
+    }
+
+    #[test]
+    fn validate_test() {
+        let kind = |builder: AprsClientBuilder| builder.build().err().map(|err| err.kind());
+
+        assert_eq!(
+            kind(AprsClientBuilder::new()),
+            Some(std::io::ErrorKind::InvalidInput)
+        );
+        assert_eq!(
+            kind(
+                AprsClientBuilder::new()
+                    .server("aprs.glidernet.org")
+                    .port(PORT::FULLFEED)
+                    .login(login_data())
+                    .filter("r/47/7/100")
+            ),
+            Some(std::io::ErrorKind::InvalidInput)
+        );
+        assert_eq!(
+            kind(
+                AprsClientBuilder::new()
+                    .server("aprs.glidernet.org")
+                    .filter("r/47/7/100")
+            ),
+            Some(std::io::ErrorKind::InvalidInput)
+        );
+        assert_eq!(
+            kind(
+                AprsClientBuilder::new()
+                    .server("aprs.glidernet.org")
+                    .retry_policy(RetryPolicy {
+                        initial_delay: Duration::from_secs(10),
+                        max_delay: Duration::from_secs(1),
+                        max_attempts: None,
+                    })
+            ),
+            Some(std::io::ErrorKind::InvalidInput)
+        );
+        assert_eq!(
+            kind(
+                AprsClientBuilder::new()
+                    .server("aprs.glidernet.org")
+                    .retry_policy(RetryPolicy {
+                        initial_delay: Duration::ZERO,
+                        ..RetryPolicy::default()
+                    })
+            ),
+            Some(std::io::ErrorKind::InvalidInput)
+        );
+
+        let client = AprsClientBuilder::new()
+            .server("aprs.glidernet.org")
+            .login(login_data())
+            .filter("r/47/7/100")
+            .build()
+            .unwrap();
+        assert!(!client.lock().unwrap().is_connected());
+    }
+
+    #[test]
+    fn build_and_connect_test() {
+        let server = MockServer::start(PORT::FILTER).unwrap();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let first = received.clone();
+        let second = received.clone();
+
+        let client = AprsClientBuilder::new()
+            .server(&format!("127.0.0.1:{}", server.port()))
+            .login(login_data())
+            .filter("r/47/7/100")
+            .callback(move |message| first.lock().unwrap().push(format!("1 {}", message.message)))
+            .callback(move |message| {
+                second
+                    .lock()
+                    .unwrap()
+                    .push(format!("2 {}", message.message))
+            })
+            .build()
+            .unwrap();
+        let mut client = client.lock().unwrap();
+
+        client.reconnect().unwrap();
+        assert!(client.is_logged_in());
+        assert!(server.wait_until(Duration::from_secs(5), |server| {
+            server.filters() == vec!["r/47/7/100".to_string()]
+        }));
+
+        server.send_line("LSTB>APRS,TCPIP*,qAC,GLIDERN2:/060854h4656.38NI00723.46E&/A=001877");
+        let message = client.read().unwrap();
+        (client.m_callback)(&message);
+        assert_eq!(received.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn keepalive_test() {
+        let server =
+            MockServer::start_with_keepalive(PORT::FILTER, Duration::from_millis(50)).unwrap();
+
+        let client = AprsClientBuilder::new()
+            .server(&format!("127.0.0.1:{}", server.port()))
+            .login(login_data())
+            .keepalive_interval(Duration::from_millis(100))
+            .build()
+            .unwrap();
+        APRSClient::run(client.clone());
+
+        assert!(server.wait_until(Duration::from_secs(5), |server| {
+            server.comments().len() >= 2
+        }));
+        assert!(server
+            .comments()
+            .iter()
+            .all(|comment| comment == "#keepalive"));
+
+        client.lock().unwrap().m_terminate = true;
+        assert_eq!(
+            AprsClientBuilder::new()
+                .server("aprs.glidernet.org")
+                .keepalive_interval(Duration::ZERO)
+                .build()
+                .err()
+                .map(|err| err.kind()),
+            Some(std::io::ErrorKind::InvalidInput)
+        );
+    }
+
+    #[test]
+    fn receive_only_login_test() {
+        let server = MockServer::start(PORT::FILTER).unwrap();
+
+        let client = AprsClientBuilder::new()
+            .server(&format!("127.0.0.1:{}", server.port()))
+            .login(LoginData::new())
+            .filter("r/47/7/100")
+            .build()
+            .unwrap();
+        let mut client = client.lock().unwrap();
+
+        client.reconnect().unwrap();
+        assert!(!client.is_verified());
+        assert!(server.wait_until(Duration::from_secs(5), |server| {
+            server.filters() == vec!["r/47/7/100".to_string()]
+        }));
+    }
+}
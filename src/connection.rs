@@ -0,0 +1,510 @@
+//! Streams underlying the client connection: plain TCP or, with the `tls` feature, TLS via rustls.
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
+
+#[cfg(feature = "tls")]
+use rustls::pki_types::pem::PemObject;
+#[cfg(feature = "tls")]
+use rustls::pki_types::{CertificateDer, ServerName};
+#[cfg(feature = "tls")]
+use rustls::{ClientConfig, ClientConnection, RootCertStore};
+#[cfg(feature = "tls")]
+use std::convert::TryFrom;
+#[cfg(feature = "tls")]
+use std::sync::{Arc, Mutex};
+
+/// Timeouts of the connection, `None` blocks indefinitely
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timeouts {
+    /// Timeout to establish the connection to a single address
+    pub connect: Duration,
+    /// The server sends a keepalive every 20 seconds, so the default of 60 seconds detects a dead
+    /// connection after missing a few keepalives
+    pub read: Option<Duration>,
+    pub write: Option<Duration>,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            connect: Duration::from_secs(10),
+            read: Some(Duration::from_secs(60)),
+            write: Some(Duration::from_secs(10)),
+        }
+    }
+}
+
+/// Delays between reconnect attempts, doubled after every failed attempt up to `max_delay`
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    /// Number of failed attempts after which the client gives up, `None` retries forever
+    pub max_attempts: Option<u32>,
+}
+
+impl RetryPolicy {
+    /// A zero initial delay would make the `run` loop reconnect without pause, an initial delay
+    /// above the maximum would never back off
+    pub fn validate(&self) -> Result<(), std::io::Error> {
+        let invalid = |message: &str| {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                message.to_string(),
+            ))
+        };
+
+        if self.initial_delay == Duration::ZERO {
+            return invalid("the initial retry delay must not be zero");
+        }
+        if self.initial_delay > self.max_delay {
+            return invalid("the initial retry delay exceeds the maximum delay");
+        }
+
+        Ok(())
+    }
+
+    /// Delay before the next attempt after `failed_attempts` failed attempts, `None` if the client
+    /// should give up
+    pub fn delay(&self, failed_attempts: u32) -> Option<Duration> {
+        if let Some(max_attempts) = self.max_attempts {
+            if failed_attempts >= max_attempts {
+                return None;
+            }
+        }
+
+        let factor = 2u32.saturating_pow(failed_attempts.saturating_sub(1));
+        Some(
+            self.initial_delay
+                .checked_mul(factor)
+                .unwrap_or(self.max_delay)
+                .min(self.max_delay),
+        )
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            max_attempts: None,
+        }
+    }
+}
+
+/// A connection to the server, cloned to get separate handles for the reader and the writer of the
+/// client
+#[derive(Debug)]
+pub enum Connection {
+    Plain(TcpStream),
+    /// The TLS session is shared between the handles, see `TlsStream`
+    #[cfg(feature = "tls")]
+    Tls(Arc<TlsStream>),
+}
+
+/// TLS session on a socket, shared by the reader and the writer handle. The session is only locked
+/// while records are processed: a read waits for data on the socket without holding it, so the
+/// writer (login, filter, uplink) is never blocked by a pending read.
+#[cfg(feature = "tls")]
+#[derive(Debug)]
+pub struct TlsStream {
+    session: Mutex<ClientConnection>,
+    socket: TcpStream,
+}
+
+#[cfg(feature = "tls")]
+impl TlsStream {
+    fn read(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            {
+                let mut session = self.session.lock().unwrap();
+                match session.reader().read(buf) {
+                    Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {}
+                    result => return result,
+                }
+            }
+
+            // blocks until data arrives or the read timeout expires, the end of the stream is
+            // passed to the session which then reports it
+            self.socket.peek(&mut [0u8; 1])?;
+
+            let mut session = self.session.lock().unwrap();
+            session.read_tls(&mut &self.socket)?;
+            session
+                .process_new_packets()
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+            self.write_tls(&mut session)?;
+        }
+    }
+
+    fn write(&self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut session = self.session.lock().unwrap();
+        let length = session.writer().write(buf)?;
+        self.write_tls(&mut session)?;
+
+        Ok(length)
+    }
+
+    fn flush(&self) -> std::io::Result<()> {
+        let mut session = self.session.lock().unwrap();
+        session.writer().flush()?;
+        self.write_tls(&mut session)
+    }
+
+    /// Sends the pending TLS records
+    fn write_tls(&self, session: &mut ClientConnection) -> std::io::Result<()> {
+        while session.wants_write() {
+            session.write_tls(&mut &self.socket)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Connection {
+    pub fn connect_plain(
+        address: &SocketAddr,
+        timeouts: &Timeouts,
+    ) -> Result<Self, std::io::Error> {
+        Ok(Connection::Plain(open_socket(address, timeouts)?))
+    }
+
+    /// Connects and completes the TLS handshake, so certificate errors are reported here and not
+    /// on the first read. The certificate is verified against the host name unless the
+    /// configuration overrides the server name.
+    #[cfg(feature = "tls")]
+    pub fn connect_tls(
+        address: &SocketAddr,
+        host: &str,
+        tls: &TlsConfig,
+        timeouts: &Timeouts,
+    ) -> Result<Self, std::io::Error> {
+        let server_name = ServerName::try_from(tls.server_name.as_deref().unwrap_or(host))
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?
+            .to_owned();
+
+        let config =
+            ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+                .with_safe_default_protocol_versions()
+                .map_err(std::io::Error::other)?
+                .with_root_certificates(tls.root_certificates.clone())
+                .with_no_client_auth();
+
+        let mut session =
+            ClientConnection::new(Arc::new(config), server_name).map_err(std::io::Error::other)?;
+        let mut socket = open_socket(address, timeouts)?;
+
+        while session.is_handshaking() {
+            session.complete_io(&mut socket)?;
+        }
+
+        Ok(Connection::Tls(Arc::new(TlsStream {
+            session: Mutex::new(session),
+            socket,
+        })))
+    }
+
+    pub fn try_clone(&self) -> Result<Self, std::io::Error> {
+        match self {
+            Connection::Plain(stream) => Ok(Connection::Plain(stream.try_clone()?)),
+            #[cfg(feature = "tls")]
+            Connection::Tls(stream) => Ok(Connection::Tls(stream.clone())),
+        }
+    }
+}
+
+fn open_socket(address: &SocketAddr, timeouts: &Timeouts) -> Result<TcpStream, std::io::Error> {
+    let socket = TcpStream::connect_timeout(address, timeouts.connect)?;
+    socket.set_read_timeout(timeouts.read)?;
+    socket.set_write_timeout(timeouts.write)?;
+
+    Ok(socket)
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Connection::Plain(stream) => stream.read(buf),
+            #[cfg(feature = "tls")]
+            Connection::Tls(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Connection::Plain(stream) => stream.write(buf),
+            #[cfg(feature = "tls")]
+            Connection::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Connection::Plain(stream) => stream.flush(),
+            #[cfg(feature = "tls")]
+            Connection::Tls(stream) => stream.flush(),
+        }
+    }
+}
+
+/// Settings of TLS connections
+#[cfg(feature = "tls")]
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    root_certificates: RootCertStore,
+    server_name: Option<String>,
+    port: Option<u16>,
+}
+
+#[cfg(feature = "tls")]
+impl TlsConfig {
+    /// Trusts the Mozilla root certificates, verifies the certificate against the host name of the
+    /// client target
+    pub fn new() -> Self {
+        Self {
+            root_certificates: webpki_roots::TLS_SERVER_ROOTS.iter().cloned().collect(),
+            server_name: None,
+            port: None,
+        }
+    }
+
+    /// Removes all trusted root certificates, e.g. to only trust a corporate CA
+    pub fn clear_root_certificates(mut self) -> Self {
+        self.root_certificates = RootCertStore::empty();
+        self
+    }
+
+    /// Adds the PEM encoded root certificates to the trusted ones
+    pub fn root_certificates_pem(mut self, pem: &[u8]) -> Result<Self, std::io::Error> {
+        let mut count = 0;
+
+        for certificate in CertificateDer::pem_slice_iter(pem) {
+            let certificate = certificate
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+            self.root_certificates
+                .add(certificate)
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+            count += 1;
+        }
+
+        if count == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "no certificate found",
+            ));
+        }
+
+        Ok(self)
+    }
+
+    /// Adds the root certificates of the PEM file to the trusted ones
+    pub fn root_certificates_pem_file(
+        self,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<Self, std::io::Error> {
+        let pem = std::fs::read(path)?;
+        self.root_certificates_pem(&pem)
+    }
+
+    /// Name the server certificate is verified against if it differs from the target host, e.g.
+    /// when connecting by ip address
+    pub fn server_name(mut self, server_name: &str) -> Self {
+        self.server_name = Some(server_name.to_string());
+        self
+    }
+
+    /// Port of the TLS listener if it differs from the port of the client
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    pub(crate) fn listener_port(&self) -> Option<u16> {
+        self.port
+    }
+}
+
+#[cfg(feature = "tls")]
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_policy_test() {
+        let policy = RetryPolicy {
+            max_attempts: Some(8),
+            ..RetryPolicy::default()
+        };
+
+        assert_eq!(policy.delay(1), Some(Duration::from_secs(1)));
+        assert_eq!(policy.delay(2), Some(Duration::from_secs(2)));
+        assert_eq!(policy.delay(4), Some(Duration::from_secs(8)));
+        assert_eq!(policy.delay(7), Some(Duration::from_secs(60)));
+        assert_eq!(policy.delay(8), None);
+        assert_eq!(
+            RetryPolicy::default().delay(1000),
+            Some(Duration::from_secs(60))
+        );
+
+        assert!(RetryPolicy::default().validate().is_ok());
+        let zero = RetryPolicy {
+            initial_delay: Duration::ZERO,
+            ..RetryPolicy::default()
+        };
+        assert!(zero.validate().is_err());
+        let inverted = RetryPolicy {
+            initial_delay: Duration::from_secs(10),
+            max_delay: Duration::from_secs(1),
+            max_attempts: None,
+        };
+        assert!(inverted.validate().is_err());
+    }
+}
+
+#[cfg(all(test, feature = "tls"))]
+mod tls_tests {
+    use super::*;
+    use rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer};
+    use rustls::{ServerConfig, ServerConnection, StreamOwned};
+    use std::io::{BufRead, BufReader, LineWriter};
+    use std::net::TcpListener;
+
+    /// Accepts a single TLS connection, sends the banner and echoes the first line
+    fn tls_server(certified_key: rcgen::CertifiedKey) -> (u16, std::thread::JoinHandle<()>) {
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(
+            certified_key.key_pair.serialize_der(),
+        ));
+        let config =
+            ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+                .with_safe_default_protocol_versions()
+                .unwrap()
+                .with_no_client_auth()
+                .with_single_cert(vec![certified_key.cert.der().clone()], key)
+                .unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let handle = std::thread::spawn(move || {
+            let (socket, _) = listener.accept().unwrap();
+            let session = ServerConnection::new(Arc::new(config)).unwrap();
+            let mut stream = StreamOwned::new(session, socket);
+
+            if stream.write_all(b"# aprsc 2.1.10-gd72a17c\r\n").is_err() {
+                return;
+            }
+
+            let mut line = String::new();
+            if BufReader::new(&mut stream).read_line(&mut line).is_ok() {
+                let _ = stream.write_all(line.as_bytes());
+            }
+        });
+
+        (port, handle)
+    }
+
+    #[test]
+    fn tls_connection_test() {
+        let certified_key = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+        let tls = TlsConfig::new()
+            .clear_root_certificates()
+            .root_certificates_pem(certified_key.cert.pem().as_bytes())
+            .unwrap()
+            .server_name("localhost");
+        let (port, server) = tls_server(certified_key);
+
+        let connection = Connection::connect_tls(
+            &([127, 0, 0, 1], port).into(),
+            "127.0.0.1",
+            &tls,
+            &Timeouts::default(),
+        )
+        .unwrap();
+        let mut writer = LineWriter::new(connection.try_clone().unwrap());
+        let mut reader = BufReader::new(connection);
+
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "# aprsc 2.1.10-gd72a17c\r\n");
+
+        writer
+            .write_all(b"user N0CALL pass -1 vers test 0.1\r\n")
+            .unwrap();
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "user N0CALL pass -1 vers test 0.1\r\n");
+
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn tls_write_during_read_test() {
+        let certified_key = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+        let tls = TlsConfig::new()
+            .clear_root_certificates()
+            .root_certificates_pem(certified_key.cert.pem().as_bytes())
+            .unwrap()
+            .server_name("localhost");
+        let (port, server) = tls_server(certified_key);
+
+        let timeouts = Timeouts {
+            read: Some(Duration::from_secs(5)),
+            ..Timeouts::default()
+        };
+        let connection =
+            Connection::connect_tls(&([127, 0, 0, 1], port).into(), "127.0.0.1", &tls, &timeouts)
+                .unwrap();
+        let mut writer = LineWriter::new(connection.try_clone().unwrap());
+        let mut reader = BufReader::new(connection);
+
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+
+        // the reader waits for the echo, which the server only sends after the write
+        let start = std::time::Instant::now();
+        let pending_read = std::thread::spawn(move || {
+            let mut line = String::new();
+            reader.read_line(&mut line).map(|_| line)
+        });
+        std::thread::sleep(Duration::from_millis(100));
+        writer.write_all(b"#filter r/47/7/100\r\n").unwrap();
+
+        assert_eq!(
+            pending_read.join().unwrap().unwrap(),
+            "#filter r/47/7/100\r\n"
+        );
+        assert!(start.elapsed() < Duration::from_secs(4));
+
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn tls_untrusted_certificate_test() {
+        let certified_key = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+        let (port, server) = tls_server(certified_key);
+
+        let tls = TlsConfig::new().server_name("localhost");
+        let error = Connection::connect_tls(
+            &([127, 0, 0, 1], port).into(),
+            "127.0.0.1",
+            &tls,
+            &Timeouts::default(),
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+        server.join().unwrap();
+        assert!(TlsConfig::new().root_certificates_pem(b"").is_err());
+    }
+}
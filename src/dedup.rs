@@ -0,0 +1,283 @@
+//! Suppression of duplicate beacons: the same beacon of an aircraft is usually heard by several
+//! receivers and relayed once per receiver.
+
+use crate::parser::OgnTransmission;
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// A receiver that relayed the beacon
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Reception {
+    pub receiver: String,
+    /// Signal to noise ratio [dB] at the receiver
+    pub signal_strength: Option<f32>,
+}
+
+/// A beacon with all receivers that relayed it, the transmission is the first one received
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MergedBeacon {
+    pub transmission: OgnTransmission,
+    pub receptions: Vec<Reception>,
+}
+
+impl MergedBeacon {
+    /// The reception with the strongest signal
+    pub fn best_reception(&self) -> Option<&Reception> {
+        self.receptions
+            .iter()
+            .filter(|reception| reception.signal_strength.is_some())
+            .max_by(|a, b| {
+                a.signal_strength
+                    .unwrap()
+                    .total_cmp(&b.signal_strength.unwrap())
+            })
+    }
+}
+
+/// Result of `Deduplicator::insert`
+#[derive(Debug, PartialEq)]
+pub struct Insertion {
+    /// The transmission is the first reception of its beacon
+    pub first_reception: bool,
+    /// The oldest beacon, released early because `max_beacons` beacons were held
+    pub evicted: Option<MergedBeacon>,
+}
+
+/// Beacons are identical if sender, timestamp and position (to the resolution of the APRS
+/// position) match
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct BeaconKey {
+    sender_id: String,
+    timestamp: DateTime<Utc>,
+    latitude: i64,
+    longitude: i64,
+}
+
+impl BeaconKey {
+    fn new(transmission: &OgnTransmission) -> Self {
+        Self {
+            sender_id: transmission.header.sender_id.clone(),
+            timestamp: transmission.body.timestamp,
+            latitude: (transmission.body.position.x * 1e6).round() as i64,
+            longitude: (transmission.body.position.y * 1e6).round() as i64,
+        }
+    }
+}
+
+/// Merges the transmissions of a beacon received within the time window into one
+/// `MergedBeacon`, which is released once the window of its first reception has passed. At most
+/// `max_beacons` beacons are held, the oldest one is returned by `insert` when the limit is
+/// reached.
+#[derive(Debug)]
+pub struct Deduplicator {
+    window: Duration,
+    max_beacons: usize,
+    pending: HashMap<BeaconKey, MergedBeacon>,
+    /// Keys in the order of their first reception
+    order: VecDeque<(Instant, BeaconKey)>,
+}
+
+impl Deduplicator {
+    pub fn new(window: Duration, max_beacons: usize) -> Self {
+        Self {
+            window,
+            max_beacons: max_beacons.max(1),
+            pending: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    pub fn insert(&mut self, transmission: OgnTransmission) -> Insertion {
+        self.insert_at(transmission, Instant::now())
+    }
+
+    /// Adds the transmission received at the given time. Further receptions by the same receiver
+    /// are ignored.
+    pub fn insert_at(&mut self, transmission: OgnTransmission, now: Instant) -> Insertion {
+        let key = BeaconKey::new(&transmission);
+        let reception = Reception {
+            receiver: transmission.header.receiver.clone(),
+            signal_strength: transmission.body.signal_strength,
+        };
+
+        if let Some(beacon) = self.pending.get_mut(&key) {
+            if beacon
+                .receptions
+                .iter()
+                .all(|known| known.receiver != reception.receiver)
+            {
+                beacon.receptions.push(reception);
+            }
+            return Insertion {
+                first_reception: false,
+                evicted: None,
+            };
+        }
+
+        let evicted = if self.pending.len() >= self.max_beacons {
+            self.order
+                .pop_front()
+                .and_then(|(_, oldest)| self.pending.remove(&oldest))
+        } else {
+            None
+        };
+
+        self.order.push_back((now, key.clone()));
+        self.pending.insert(
+            key,
+            MergedBeacon {
+                transmission,
+                receptions: vec![reception],
+            },
+        );
+
+        Insertion {
+            first_reception: true,
+            evicted,
+        }
+    }
+
+    pub fn drain_expired(&mut self) -> Vec<MergedBeacon> {
+        self.drain_expired_at(Instant::now())
+    }
+
+    /// Beacons whose window has passed at the given time, in the order of their first reception
+    pub fn drain_expired_at(&mut self, now: Instant) -> Vec<MergedBeacon> {
+        let mut expired = Vec::new();
+
+        while let Some((first_seen, _)) = self.order.front() {
+            if now.saturating_duration_since(*first_seen) < self.window {
+                break;
+            }
+
+            let (_, key) = self.order.pop_front().unwrap();
+            expired.extend(self.pending.remove(&key));
+        }
+
+        expired
+    }
+
+    /// Releases all beacons, e.g. when shutting down
+    pub fn flush(&mut self) -> Vec<MergedBeacon> {
+        let pending = &mut self.pending;
+
+        self.order
+            .drain(..)
+            .filter_map(|(_, key)| pending.remove(&key))
+            .collect()
+    }
+
+    /// Number of beacons waiting for further receptions
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+impl Default for Deduplicator {
+    /// Five seconds covers the relay delays of the receivers
+    fn default() -> Self {
+        Self::new(Duration::from_secs(5), 10_000)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parse;
+
+    fn transmission(receiver: &str, body: &str) -> OgnTransmission {
+        OgnTransmission::parse(&format!("FLRDDA5BA>APRS,qAS,{}:{}", receiver, body)).unwrap()
+    }
+
+    const BEACON: &str =
+        "/074548h5111.32N/00102.04W'086/007/A=000607 !W80! id0ADDE626 -019fpm +0.0rot 5.5dB";
+    const STRONGER: &str =
+        "/074548h5111.32N/00102.04W'086/007/A=000607 !W80! id0ADDE626 -019fpm +0.0rot 12.0dB";
+    const NEXT_BEACON: &str =
+        "/074550h5111.33N/00102.05W'086/007/A=000607 !W80! id0ADDE626 -019fpm +0.0rot 5.5dB";
+
+    #[test]
+    fn merge_test() {
+        let mut deduplicator = Deduplicator::new(Duration::from_secs(5), 100);
+        let start = Instant::now();
+
+        assert!(
+            deduplicator
+                .insert_at(transmission("LFMX", BEACON), start)
+                .first_reception
+        );
+        assert!(
+            !deduplicator
+                .insert_at(transmission("LFNA", STRONGER), start)
+                .first_reception
+        );
+        assert!(
+            !deduplicator
+                .insert_at(transmission("LFMX", BEACON), start)
+                .first_reception
+        );
+        assert!(
+            deduplicator
+                .insert_at(transmission("LFMX", NEXT_BEACON), start)
+                .first_reception
+        );
+        assert!(deduplicator.drain_expired_at(start).is_empty());
+
+        let merged = deduplicator.drain_expired_at(start + Duration::from_secs(5));
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].transmission.header.receiver, "LFMX");
+        assert_eq!(
+            merged[0].receptions,
+            vec![
+                Reception {
+                    receiver: "LFMX".to_string(),
+                    signal_strength: Some(5.5),
+                },
+                Reception {
+                    receiver: "LFNA".to_string(),
+                    signal_strength: Some(12.0),
+                },
+            ]
+        );
+        assert_eq!(merged[0].best_reception().unwrap().receiver, "LFNA");
+        let mut not_a_number = merged[0].receptions.clone();
+        not_a_number.push(Reception {
+            receiver: "LFNB".to_string(),
+            signal_strength: Some(f32::NAN),
+        });
+        let beacon = MergedBeacon {
+            transmission: transmission("LFMX", BEACON),
+            receptions: not_a_number,
+        };
+        assert!(beacon.best_reception().is_some());
+        assert_eq!(merged[1].receptions.len(), 1);
+        assert_eq!(deduplicator.pending(), 0);
+    }
+
+    #[test]
+    fn bounded_memory_test() {
+        let mut deduplicator = Deduplicator::new(Duration::from_secs(5), 1);
+        let start = Instant::now();
+
+        deduplicator.insert_at(transmission("LFMX", BEACON), start);
+        let insertion = deduplicator.insert_at(transmission("LFMX", NEXT_BEACON), start);
+        assert_eq!(deduplicator.pending(), 1);
+
+        // the first beacon has been released early
+        let evicted = insertion.evicted.unwrap();
+        assert!(deduplicator.drain_expired_at(start).is_empty());
+        assert_eq!(
+            evicted
+                .transmission
+                .body
+                .timestamp
+                .format("%H%M%S")
+                .to_string(),
+            "074548"
+        );
+        assert_eq!(deduplicator.flush().len(), 1);
+    }
+}
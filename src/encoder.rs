@@ -0,0 +1,266 @@
+use crate::parser::{OgnBody, OgnHeader, OgnTransmission};
+use crate::OgnStatusMessage;
+
+/// Counterpart to the `Parse` trait, renders a value into the (OGN flavoured) APRS wire format
+pub trait Encode {
+    fn to_aprs_string(&self) -> String;
+}
+
+impl Encode for OgnTransmission {
+    fn to_aprs_string(&self) -> String {
+        format!(
+            "{}:{}",
+            self.header.to_aprs_string(),
+            self.body.to_aprs_string()
+        )
+    }
+}
+
+impl Encode for OgnHeader {
+    fn to_aprs_string(&self) -> String {
+        let mut header = format!("{}>{}", self.sender_id, self.target);
+        for station in &self.path {
+            header.push(',');
+            header.push_str(station);
+        }
+        header.push_str(&format!(",{},{}", self.transmission_method, self.receiver));
+
+        header
+    }
+}
+
+impl Encode for OgnBody {
+    /// Mic-E reports are rendered as regular position reports with timestamp
+    fn to_aprs_string(&self) -> String {
+        let (lat_degrees, lat_minutes) =
+            to_degrees_minutes(self.position.x, self.precision_enhancement);
+        let (lon_degrees, lon_minutes) =
+            to_degrees_minutes(self.position.y, self.precision_enhancement);
+
+        let mut body = format!(
+            "/{}h{:02}{:02}.{:02}{}{}{:03}{:02}.{:02}{}{}",
+            self.timestamp.format("%H%M%S"),
+            lat_degrees,
+            lat_minutes / 1000,
+            lat_minutes % 1000 / 10,
+            if self.position.x < 0.0 { 'S' } else { 'N' },
+            self.symbol_table,
+            lon_degrees,
+            lon_minutes / 1000,
+            lon_minutes % 1000 / 10,
+            if self.position.y < 0.0 { 'W' } else { 'E' },
+            self.symbol_code,
+        );
+
+        if self.ground_track.is_some() || self.ground_speed.is_some() {
+            body.push_str(&format!(
+                "{:03}/{:03}",
+                self.ground_track.unwrap_or(0),
+                self.ground_speed
+                    .map(|v| v.knots().round().clamp(0.0, 999.0) as u32)
+                    .unwrap_or(0)
+            ));
+        }
+
+        if let Some(altitude) = self.altitude {
+            body.push_str(&format!("/A={:06}", altitude.feet()));
+        }
+
+        // ogn extras, the precision enhancement carries the third decimal digit of the minutes
+        if self.precision_enhancement {
+            body.push_str(&format!(" !W{}{}!", lat_minutes % 10, lon_minutes % 10));
+        }
+
+        if let Some(id) = &self.id {
+            body.push_str(&format!(" id{}", id));
+        }
+        if let Some(climb_rate) = self.climb_rate {
+            body.push_str(&format!(
+                " {:+04}fpm",
+                climb_rate.feet_per_minute().round() as i32
+            ));
+        }
+        if let Some(turn_rate) = self.ground_turning_rate {
+            body.push_str(&format!(" {:+.1}rot", turn_rate.half_turns_per_minute()));
+        }
+        if let Some(signal_strength) = self.signal_strength {
+            body.push_str(&format!(" {:.1}dB", signal_strength));
+        }
+        if let Some(gps_accuracy) = &self.gps_accuracy {
+            body.push_str(&format!(" gps{}", gps_accuracy));
+        }
+
+        body
+    }
+}
+
+impl Encode for OgnStatusMessage {
+    /// Renders the `Key=Value` pairs of the status line without the leading `>` and timestamp,
+    /// spaces within the values are replaced by `_` (`validate` rejects values containing `_`)
+    fn to_aprs_string(&self) -> String {
+        self.fields()
+            .iter()
+            .filter_map(|(key, value)| {
+                value
+                    .as_ref()
+                    .map(|value| format!("{}={}", key, value.replace(' ', "_")))
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Splits decimal degrees into whole degrees and thousandths of minutes, rounded to hundredths of
+/// minutes without precision enhancement
+fn to_degrees_minutes(value: f64, precision_enhancement: bool) -> (u32, u32) {
+    let value = value.abs();
+    let step = if precision_enhancement { 1.0 } else { 10.0 };
+    let mut degrees = value.floor() as u32;
+    let mut minute_thousandths =
+        (((value - degrees as f64) * 60_000.0 / step).round() * step) as u32;
+
+    if minute_thousandths >= 60_000 {
+        degrees += 1;
+        minute_thousandths -= 60_000;
+    }
+
+    (degrees, minute_thousandths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{to_decimal_degrees, Parse};
+    use crate::units::{Feet, FeetPerMinute, HalfTurnsPerMinute, Knots};
+    use chrono::prelude::*;
+    use geocoding::Coordinate;
+    use proptest::prelude::*;
+
+    #[test]
+    fn encode_transmission_test() {
+        let message = r#"OGN82149C>OGNTRK,qAS,OxfBarton:/130208h5145.95N/00111.50W'232/000/A=000295 !W33! id3782149C +000fpm -4.3rot FL000.00 55.0dB 0e -3.7kHz gps3x5"#;
+        let transmission = OgnTransmission::parse(message).unwrap();
+
+        assert_eq!(
+            transmission.to_aprs_string(),
+            r#"OGN82149C>OGNTRK,qAS,OxfBarton:/130208h5145.95N/00111.50W'232/000/A=000295 !W33! id3782149C +000fpm -4.3rot 55.0dB gps3x5"#
+        );
+
+        // receiver beacon without course, speed and ogn extras, encoded as received
+        let message = r"LSTB>APRS,TCPIP*,qAC,GLIDERN2:/060854h4656.38NI00723.46E&/A=001877";
+        let transmission = OgnTransmission::parse(message).unwrap();
+
+        assert_eq!(transmission.to_aprs_string(), message);
+    }
+
+    #[test]
+    fn encode_status_test() {
+        let status = OgnStatusMessage::builder()
+            .pilot_name("John Smith")
+            .model("Discus 2c")
+            .competition_id("JS")
+            .hardware("v1.2")
+            .build()
+            .unwrap();
+
+        let encoded = status.to_aprs_string();
+        assert_eq!(encoded, "Pilot=John_Smith Model=Discus_2c ID=JS Hard=v1.2");
+        assert_eq!(OgnStatusMessage::parse(&encoded), Some(status));
+    }
+
+    #[test]
+    fn degrees_minutes_test() {
+        assert_eq!(to_degrees_minutes(51.0 + 45.953 / 60.0, true), (51, 45953));
+        assert_eq!(to_degrees_minutes(-(1.0 + 11.503 / 60.0), true), (1, 11503));
+        // hundredths of minutes without precision enhancement
+        assert_eq!(to_degrees_minutes(51.0 + 45.953 / 60.0, false), (51, 45950));
+        assert_eq!(to_degrees_minutes(51.0 + 45.957 / 60.0, false), (51, 45960));
+        // rounding up to the next degree
+        assert_eq!(to_degrees_minutes(47.9999999, true), (48, 0));
+        assert_eq!(to_degrees_minutes(47.0 + 59.996 / 60.0, false), (48, 0));
+    }
+
+    /// Degrees, thousandths of minutes and hemisphere of a coordinate
+    fn coordinate(max_degrees: u32) -> impl Strategy<Value = (u32, u32, bool)> {
+        (0..max_degrees, 0..60_000u32, any::<bool>())
+    }
+
+    /// Without precision enhancement the minutes have two decimals only
+    fn to_value(
+        (degrees, minutes, negative): (u32, u32, bool),
+        precision_enhancement: bool,
+    ) -> f64 {
+        let minutes = if precision_enhancement {
+            minutes
+        } else {
+            minutes - minutes % 10
+        };
+        let value = to_decimal_degrees(degrees, minutes);
+        if negative {
+            -value
+        } else {
+            value
+        }
+    }
+
+    prop_compose! {
+        fn header()(
+            sender_id in "[A-Z0-9]{3,9}",
+            target in "[A-Z]{4,6}",
+            path in prop::collection::vec("TCPIP\\*|[A-Z]{4}[0-9](-[0-9])?\\*?", 0..3),
+            transmission_method in "qA[SCRO]",
+            receiver in "[A-Za-z0-9]{3,9}",
+        ) -> OgnHeader {
+            OgnHeader { sender_id, target, path, receiver, transmission_method }
+        }
+    }
+
+    prop_compose! {
+        fn body()(
+            seconds in 0..4_000_000_000i64,
+            latitude in coordinate(90),
+            longitude in coordinate(180),
+            precision_enhancement in any::<bool>(),
+            symbol_table in prop::sample::select(vec!['/', '\\', 'I']),
+            symbol_code in prop::sample::select(vec!['\'', 'z', 'X', 'g', '^', 'O', 'n', '&']),
+            course in prop::option::of((0..=360u16, 0..=999u16)),
+            altitude in prop::option::of(-9999..99999i32),
+            id in prop::option::of("[0-9A-F]{8}"),
+            climb_rate in prop::option::of(-9999..9999i32),
+            turn_rate in prop::option::of(-200..200i32),
+            gps_accuracy in prop::option::of("[0-9]{1,2}x[0-9]{1,2}"),
+            signal_strength in prop::option::of(0..600i32),
+        ) -> OgnBody {
+            OgnBody {
+                timestamp: Utc.timestamp(seconds, 0),
+                position: Coordinate {
+                    x: to_value(latitude, precision_enhancement),
+                    y: to_value(longitude, precision_enhancement),
+                },
+                precision_enhancement,
+                symbol_table,
+                symbol_code,
+                ground_speed: course.map(|(_, speed)| Knots(speed as f32)),
+                ground_turning_rate: turn_rate.map(|v| HalfTurnsPerMinute(v as f32 / 10.0)),
+                climb_rate: climb_rate.map(|v| FeetPerMinute(v as f32)),
+                altitude: altitude.map(Feet),
+                ground_track: course.map(|(track, _)| track),
+                gps_accuracy,
+                signal_strength: signal_strength.map(|v| v as f32 / 10.0),
+                id,
+                mic_e: None,
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn round_trip(header in header(), body in body()) {
+            let transmission = OgnTransmission { header, body };
+            let encoded = transmission.to_aprs_string();
+
+            let parsed = OgnTransmission::parse_at(&encoded, transmission.body.timestamp);
+            prop_assert_eq!(parsed, Some(transmission), "encoded: {}", encoded);
+        }
+    }
+}
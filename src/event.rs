@@ -0,0 +1,34 @@
+//! Events reporting the state of the connection, e.g. to show the link health
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// Change of the connection state, passed to the event callback of the client
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ConnectionEvent {
+    /// Connecting to a server, also emitted when failing over to the next server
+    Connecting,
+    /// The server sent its welcome message, `server` is its address if known to the transport
+    Connected {
+        server: Option<SocketAddr>,
+    },
+    /// The server answered the login, only verified logins allow uplink
+    LoggedIn {
+        verified: bool,
+    },
+    FilterApplied {
+        filter: String,
+    },
+    Disconnected {
+        reason: String,
+    },
+    /// The `run` loop tries to reconnect after `delay`, `attempt` starts at 1 for every lost
+    /// connection
+    Reconnecting {
+        attempt: u32,
+        delay: Duration,
+    },
+}
+
+pub type EventCallback = Box<dyn Fn(&ConnectionEvent) + Send>;
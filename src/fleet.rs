@@ -0,0 +1,233 @@
+//! Live table of the latest state of every aircraft in the feed
+
+use crate::aircraft::OgnId;
+use crate::geo::distance_km;
+use crate::parser::{OgnTransmission, Parse};
+use crate::units::{Feet, FeetPerMinute, Knots};
+use chrono::{DateTime, Utc};
+use geocoding::Coordinate;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Latest known state of an aircraft
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AircraftState {
+    pub sender_id: String,
+    pub id: Option<OgnId>,
+    /// x: latitude, y: longitude [degrees]
+    #[cfg_attr(feature = "serde", serde(with = "crate::parser::position_serde"))]
+    pub position: Coordinate<f64>,
+    pub altitude: Option<Feet>,
+    pub ground_speed: Option<Knots>,
+    pub ground_track: Option<u16>,
+    pub climb_rate: Option<FeetPerMinute>,
+    /// Time of the position as sent by the aircraft
+    pub timestamp: DateTime<Utc>,
+    pub receiver: String,
+    /// Reception time of the last update
+    pub last_seen: DateTime<Utc>,
+}
+
+/// Keeps the latest state per aircraft (by sender id) and forgets aircraft that have not been
+/// seen within the timeout. Only beacons with an OGN id are tracked, so receiver beacons and other
+/// stations are ignored.
+#[derive(Debug)]
+pub struct FleetTracker {
+    timeout: Duration,
+    aircraft: HashMap<String, AircraftState>,
+}
+
+impl FleetTracker {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            aircraft: HashMap::new(),
+        }
+    }
+
+    pub fn update(&mut self, transmission: &OgnTransmission) -> bool {
+        self.update_at(transmission, Utc::now())
+    }
+
+    /// Updates the aircraft with the transmission received at the given time, returns whether
+    /// the state changed. Positions older than the known one (late relays) are ignored.
+    pub fn update_at(&mut self, transmission: &OgnTransmission, received: DateTime<Utc>) -> bool {
+        let body = &transmission.body;
+        if body.id.is_none() {
+            return false;
+        }
+
+        let sender_id = &transmission.header.sender_id;
+        if let Some(known) = self.aircraft.get(sender_id) {
+            if body.timestamp <= known.timestamp {
+                return false;
+            }
+        }
+
+        self.aircraft.insert(
+            sender_id.clone(),
+            AircraftState {
+                sender_id: sender_id.clone(),
+                id: body.id.as_deref().and_then(OgnId::parse),
+                position: body.position,
+                altitude: body.altitude,
+                ground_speed: body.ground_speed,
+                ground_track: body.ground_track,
+                climb_rate: body.climb_rate,
+                timestamp: body.timestamp,
+                receiver: transmission.header.receiver.clone(),
+                last_seen: received,
+            },
+        );
+
+        true
+    }
+
+    pub fn expire(&mut self) -> Vec<AircraftState> {
+        self.expire_at(Utc::now())
+    }
+
+    /// Removes and returns the aircraft not seen within the timeout before the given time
+    pub fn expire_at(&mut self, now: DateTime<Utc>) -> Vec<AircraftState> {
+        let timeout =
+            chrono::Duration::from_std(self.timeout).unwrap_or(chrono::Duration::max_value());
+        let expired: Vec<String> = self
+            .aircraft
+            .values()
+            .filter(|state| now.signed_duration_since(state.last_seen) > timeout)
+            .map(|state| state.sender_id.clone())
+            .collect();
+
+        expired
+            .iter()
+            .filter_map(|sender_id| self.aircraft.remove(sender_id))
+            .collect()
+    }
+
+    pub fn get(&self, sender_id: &str) -> Option<&AircraftState> {
+        self.aircraft.get(sender_id)
+    }
+
+    pub fn aircraft(&self) -> impl Iterator<Item = &AircraftState> {
+        self.aircraft.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.aircraft.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.aircraft.is_empty()
+    }
+
+    /// Aircraft within the radius [km] around the center, nearest first
+    pub fn within(&self, center: Coordinate<f64>, radius_km: f64) -> Vec<&AircraftState> {
+        let mut nearby: Vec<(f64, &AircraftState)> = self
+            .aircraft
+            .values()
+            .map(|state| (distance_km(center, state.position), state))
+            .filter(|(distance, _)| *distance <= radius_km)
+            .collect();
+        nearby.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        nearby.into_iter().map(|(_, state)| state).collect()
+    }
+
+    /// Aircraft received after the given time, most recent first
+    pub fn updated_since(&self, since: DateTime<Utc>) -> Vec<&AircraftState> {
+        let mut updated: Vec<&AircraftState> = self
+            .aircraft
+            .values()
+            .filter(|state| state.last_seen > since)
+            .collect();
+        updated.sort_by_key(|state| std::cmp::Reverse(state.last_seen));
+
+        updated
+    }
+}
+
+impl Default for FleetTracker {
+    /// Aircraft are forgotten after 10 minutes
+    fn default() -> Self {
+        Self::new(Duration::from_secs(600))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn reference() -> DateTime<Utc> {
+        Utc.ymd(2021, 6, 1).and_hms(12, 0, 0)
+    }
+
+    fn transmission(sender: &str, body: &str) -> OgnTransmission {
+        OgnTransmission::parse_at(&format!("{}>APRS,qAS,LFMX:{}", sender, body), reference())
+            .unwrap()
+    }
+
+    #[test]
+    fn update_test() {
+        let mut fleet = FleetTracker::new(Duration::from_secs(600));
+        let received = reference();
+
+        let beacon = transmission(
+            "FLRDDA5BA",
+            "/115958h4656.38N/00723.46E'086/045/A=004500 !W80! id06DDA5BA +198fpm +0.0rot",
+        );
+        assert!(fleet.update_at(&beacon, received));
+        // duplicate and late relays
+        assert!(!fleet.update_at(&beacon, received));
+        let older = transmission(
+            "FLRDDA5BA",
+            "/115950h4656.30N/00723.40E'086/045/A=004400 !W80! id06DDA5BA +198fpm +0.0rot",
+        );
+        assert!(!fleet.update_at(&older, received));
+
+        // receiver beacon without id
+        let receiver = transmission("LSTB", "/115958h4656.38NI00723.46E&/A=001877");
+        assert!(!fleet.update_at(&receiver, received));
+
+        let state = fleet.get("FLRDDA5BA").unwrap();
+        assert_eq!(state.altitude, Some(Feet(4500)));
+        assert_eq!(state.ground_speed, Some(Knots(45.0)));
+        assert_eq!(state.id.unwrap().address, 0xDDA5BA);
+        assert_eq!(state.receiver, "LFMX");
+        assert_eq!(fleet.len(), 1);
+    }
+
+    #[test]
+    fn query_and_expire_test() {
+        let mut fleet = FleetTracker::new(Duration::from_secs(600));
+        let start = reference();
+
+        // Bern and Zurich
+        let bern = transmission(
+            "FLRDDA5BA",
+            "/115958h4656.38N/00723.46E'086/045/A=004500 !W80! id06DDA5BA",
+        );
+        let zurich = transmission(
+            "FLRDDB1C2",
+            "/115958h4722.50N/00832.50E'086/045/A=004500 !W80! id06DDB1C2",
+        );
+        fleet.update_at(&bern, start);
+        fleet.update_at(&zurich, start + chrono::Duration::minutes(5));
+
+        let center = Coordinate { x: 46.95, y: 7.45 };
+        let nearby = fleet.within(center, 20.0);
+        assert_eq!(nearby.len(), 1);
+        assert_eq!(nearby[0].sender_id, "FLRDDA5BA");
+        assert_eq!(fleet.within(center, 200.0)[1].sender_id, "FLRDDB1C2");
+
+        let updated = fleet.updated_since(start);
+        assert_eq!(updated.len(), 1);
+        assert_eq!(updated[0].sender_id, "FLRDDB1C2");
+
+        let expired = fleet.expire_at(start + chrono::Duration::minutes(11));
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].sender_id, "FLRDDA5BA");
+        assert_eq!(fleet.len(), 1);
+    }
+}
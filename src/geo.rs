@@ -0,0 +1,35 @@
+//! Geodesy helpers shared by the mock server, the fleet tracker, the tracks and the logbook
+
+use geocoding::Coordinate;
+
+/// Great circle distance, x: latitude, y: longitude [degrees]
+pub(crate) fn distance_km(a: Coordinate<f64>, b: Coordinate<f64>) -> f64 {
+    let (latitude_a, latitude_b) = (a.x.to_radians(), b.x.to_radians());
+    let delta_latitude = latitude_b - latitude_a;
+    let delta_longitude = (b.y - a.y).to_radians();
+
+    let h = (delta_latitude / 2.0).sin().powi(2)
+        + latitude_a.cos() * latitude_b.cos() * (delta_longitude / 2.0).sin().powi(2);
+
+    2.0 * 6371.0 * h.sqrt().asin()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distance_test() {
+        let bern = Coordinate {
+            x: 46.948,
+            y: 7.447,
+        };
+        let zurich = Coordinate {
+            x: 47.377,
+            y: 8.540,
+        };
+
+        assert!((distance_km(bern, zurich) - 95.5).abs() < 1.0);
+        assert_eq!(distance_km(bern, bern), 0.0);
+    }
+}
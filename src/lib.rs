@@ -2,59 +2,231 @@
 #[macro_use]
 extern crate pretty_assertions;
 
-use chrono::Timelike;
-use chrono::Utc;
-use log::{debug, error, info};
+use chrono::{DateTime, Utc};
+use geocoding::Coordinate;
+use log::{debug, error, info, warn};
 use std::fmt::Debug;
-use std::io::{BufRead, BufReader, LineWriter, Write};
-use std::net::TcpStream;
 use std::sync::Arc;
 use std::sync::Mutex;
-
+use std::time::Instant;
+
+use aircraft::OgnId;
+use builder::AprsClientBuilder;
+use connection::RetryPolicy;
+use encoder::Encode;
+use event::{ConnectionEvent, EventCallback};
+use parser::{OgnBody, Parse, ServerComment};
+use rate_limit::TokenBucket;
+use scheduler::PositionScheduler;
+use transport::{TcpTransport, Transport};
+use units::{Feet, FeetPerMinute, HalfTurnsPerMinute, Knots};
+
+pub mod aircraft;
+pub mod builder;
+pub mod connection;
+pub mod dedup;
+pub mod encoder;
+pub mod event;
+pub mod fleet;
+mod geo;
+pub mod logbook;
 pub mod parser;
+pub mod rate_limit;
+pub mod scheduler;
+#[cfg(any(test, feature = "test-server"))]
+pub mod test_server;
+pub mod timestamp;
+pub mod track;
+pub mod transport;
+pub mod units;
 
-#[repr(u16)]
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PORT {
   /// Subscribe to the full feed (note: no filtering but does not require authentication)
-  FULLFEED = 10152,
+  FULLFEED,
   /// Port on which filtering is supported
-  FILTER = 14580,
+  FILTER,
+  /// Any other port, e.g. of a local test server or a relay, with the features it supports
+  Custom(u16, PortCapabilities),
+}
+
+impl PORT {
+  pub fn number(&self) -> u16 {
+    match self {
+      PORT::FULLFEED => 10152,
+      PORT::FILTER => 14580,
+      PORT::Custom(port, _) => *port,
+    }
+  }
+
+  pub fn capabilities(&self) -> PortCapabilities {
+    match self {
+      PORT::FULLFEED => PortCapabilities::FULL_FEED,
+      PORT::FILTER => PortCapabilities::FILTER,
+      PORT::Custom(_, capabilities) => capabilities.clone(),
+    }
+  }
+}
+
+/// Features supported by a server port
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PortCapabilities {
+  /// Accepts `#filter` commands and only sends matching lines
+  pub filter: bool,
+  /// Sends all lines without filtering
+  pub full_feed: bool,
+  /// Accepts positions and status messages from verified clients
+  pub uplink: bool,
+}
+
+impl PortCapabilities {
+  pub const FULL_FEED: PortCapabilities = PortCapabilities {
+    filter: false,
+    full_feed: true,
+    uplink: true,
+  };
+  pub const FILTER: PortCapabilities = PortCapabilities {
+    filter: true,
+    full_feed: false,
+    uplink: true,
+  };
 }
 
-pub struct APRSClient {
-  m_target: String,
+/// Maximum length of a line read from the server including the line terminator, as in the
+/// APRS-IS specification
+pub const MAX_LINE_LENGTH: usize = 512;
+
+/// Decoding of the bytes read from the server, the feed occasionally contains Latin-1 or garbage
+/// bytes in comments
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum LineDecoding {
+  /// Invalid UTF-8 sequences are replaced by U+FFFD
+  Utf8Lossy,
+  /// Every byte is a character
+  Latin1,
+  /// Valid UTF-8 as is, otherwise Latin-1
+  #[default]
+  Utf8OrLatin1,
+}
+
+impl LineDecoding {
+  pub fn decode(&self, bytes: &[u8]) -> String {
+    match (self, std::str::from_utf8(bytes)) {
+      (LineDecoding::Utf8Lossy, _) => String::from_utf8_lossy(bytes).into_owned(),
+      (LineDecoding::Utf8OrLatin1, Ok(line)) => line.to_string(),
+      (LineDecoding::Latin1, _) | (LineDecoding::Utf8OrLatin1, Err(_)) => {
+        bytes.iter().map(|byte| *byte as char).collect()
+      }
+    }
+  }
+}
+
+/// A line read from the server together with its reception metadata
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReceivedMessage {
+  /// The line without the line terminator, decoded according to the `LineDecoding` of the client
+  pub message: String,
+  /// The exact bytes of the line without the line terminator
+  pub raw: Vec<u8>,
+  /// Number of bytes read from the connection including the line terminator
+  pub length: usize,
+  /// Monotonic reception time, use this for latency measurements (not serialized)
+  #[cfg_attr(feature = "serde", serde(skip, default = "Instant::now"))]
+  pub received_at: Instant,
+  /// Reception time in UTC, e.g. as reference to resolve the message timestamps
+  pub received_utc: DateTime<Utc>,
+  /// Server name as reported in the login response
+  pub server: Option<String>,
+  /// Incremented with every (re)connect of the client
+  pub connection_id: u64,
+  /// Sequence number of the line within the connection, starting at 0 with the server banner
+  pub sequence: u64,
+}
+
+pub struct APRSClient<T: Transport = TcpTransport> {
   m_port: PORT,
-  m_reader: Option<BufReader<TcpStream>>,
-  m_writer: Option<LineWriter<TcpStream>>,
-  m_callback: Box<dyn Fn(&str) + Send>,
+  m_transport: T,
+  m_callback: Box<dyn Fn(&ReceivedMessage) + Send>,
   m_thread: Option<std::thread::JoinHandle<()>>,
   m_terminate: bool,
   m_logged_in: bool,
+  m_verified: bool,
   m_user: Option<String>,
   m_is_connected: bool,
+  m_server: Option<String>,
+  m_connection_id: u64,
+  m_sequence: u64,
+  m_rate_limiter: Option<TokenBucket>,
+  m_position_scheduler: PositionScheduler,
+  m_login_data: Option<LoginData>,
+  m_filter: Option<String>,
+  m_retry_policy: RetryPolicy,
+  m_event_callback: Option<EventCallback>,
+  m_max_line_length: usize,
+  m_line_decoding: LineDecoding,
+  m_banner: Option<ServerComment>,
+  m_clock_skew: Option<chrono::Duration>,
+  m_keepalive_interval: Option<std::time::Duration>,
+  m_last_sent: Instant,
 }
 
-impl APRSClient {
-  // ------------------------------------------------------------------------------
-  // Public interface
-  // ------------------------------------------------------------------------------
-  pub fn new(target: &str, port: PORT, callback: Box<dyn Fn(&str) + Send>) -> Arc<Mutex<Self>> {
+impl APRSClient<TcpTransport> {
+  /// Collects the configuration of a client, see `AprsClientBuilder`
+  pub fn builder() -> AprsClientBuilder {
+    AprsClientBuilder::new()
+  }
+
+  pub fn new(
+    target: &str,
+    port: PORT,
+    callback: Box<dyn Fn(&ReceivedMessage) + Send>,
+  ) -> Arc<Mutex<Self>> {
     // ip addr
     info!("creating aprs client with target '{}:{:#?}'", target, port);
 
-    let client = Arc::new(Mutex::new(APRSClient {
-      m_port: port,
-      m_target: target.to_string(),
-      m_writer: None,
-      m_reader: None,
-      m_callback: callback,
-      m_thread: None,
-      m_terminate: false,
-      m_logged_in: false,
-      m_user: None,
-      m_is_connected: false,
-    }));
+    let transport = TcpTransport::new(target, port.number());
+    let client = Arc::new(Mutex::new(APRSClient::create(transport, port, callback)));
+
+    // lock scope
+    {
+      let mut locked_client = client.lock().unwrap();
+
+      // try to connect to the server
+      let _ = locked_client.connect();
+    }
+
+    client
+  }
+
+  /// Creates a client without connecting, so applications can start up offline. Call `connect`
+  /// (or `run`) once the network is available.
+  pub fn new_lazy(
+    target: &str,
+    port: PORT,
+    callback: Box<dyn Fn(&ReceivedMessage) + Send>,
+  ) -> Arc<Mutex<Self>> {
+    info!("creating lazy aprs client with target '{}:{:#?}'", target, port);
+
+    let transport = TcpTransport::new(target, port.number());
+    Arc::new(Mutex::new(APRSClient::create(transport, port, callback)))
+  }
+
+  /// Creates a client connecting via TLS, see `TlsConfig` for the trusted certificates and the
+  /// port of the TLS listener
+  #[cfg(feature = "tls")]
+  pub fn new_tls(
+    target: &str,
+    port: PORT,
+    callback: Box<dyn Fn(&ReceivedMessage) + Send>,
+    tls: connection::TlsConfig,
+  ) -> Arc<Mutex<Self>> {
+    info!("creating aprs client with tls target '{}:{:#?}'", target, port);
+
+    let transport = TcpTransport::new(target, port.number()).tls(tls);
+    let client = Arc::new(Mutex::new(APRSClient::create(transport, port, callback)));
 
     // lock scope
     {
@@ -67,66 +239,139 @@ impl APRSClient {
     client
   }
 
+  /// Creates a client failing over between the servers, given as `host` or `host:port` (the
+  /// port defaults to `port`). All addresses of the servers are tried, starting with the last
+  /// server the client successfully connected to.
+  pub fn with_servers(
+    servers: &[&str],
+    port: PORT,
+    callback: Box<dyn Fn(&ReceivedMessage) + Send>,
+  ) -> Arc<Mutex<Self>> {
+    info!("creating aprs client with servers {:?}", servers);
+
+    let transport = TcpTransport::with_servers(servers, port.number());
+    let client = Arc::new(Mutex::new(APRSClient::create(transport, port, callback)));
+
+    // lock scope
+    {
+      let mut locked_client = client.lock().unwrap();
+
+      // try to connect to the server
+      let _ = locked_client.connect();
+    }
+
+    client
+  }
+}
+
+impl<T: Transport> APRSClient<T> {
+  // ------------------------------------------------------------------------------
+  // Public interface
+  // ------------------------------------------------------------------------------
+  /// Creates a client on the given transport without connecting, e.g. a `MemoryTransport` in
+  /// tests or a `FileTransport` to replay a recorded feed
+  pub fn with_transport(
+    transport: T,
+    port: PORT,
+    callback: Box<dyn Fn(&ReceivedMessage) + Send>,
+  ) -> Arc<Mutex<Self>> {
+    Arc::new(Mutex::new(APRSClient::create(transport, port, callback)))
+  }
+
   pub fn is_connected(&self) -> bool {
     self.m_is_connected
   }
 
-  pub fn connect(&mut self) -> Result<(), std::io::Error> {
-    let target = format!("{}:{}", self.m_target, self.m_port.clone() as u16);
+  /// Name of the server as reported in the login response
+  pub fn server(&self) -> Option<&str> {
+    self.m_server.as_deref()
+  }
 
-    info!("trying to connect to {}...", target);
+  /// The welcome message of the current connection, e.g. the server software and version
+  pub fn banner(&self) -> Option<&ServerComment> {
+    self.m_banner.as_ref()
+  }
 
-    let connection = TcpStream::connect(target);
+  /// Difference between the local time and the server time of the last keepalive, positive if
+  /// the local clock is ahead
+  pub fn clock_skew(&self) -> Option<chrono::Duration> {
+    self.m_clock_skew
+  }
+
+  /// Address of the server currently connected to
+  pub fn peer_address(&self) -> Option<std::net::SocketAddr> {
+    self.m_transport.peer_address()
+  }
 
-    match connection {
+  /// Connects to the server, fails over to the next server if the connection fails before the
+  /// welcome message is received
+  pub fn connect(&mut self) -> Result<(), std::io::Error> {
+    self.emit(ConnectionEvent::Connecting);
+    match self.m_transport.connect() {
       Ok(_) => info!("...connection successfully established"),
       Err(err) => {
         error!("{}", err);
+        self.m_is_connected = false;
         return Err(err);
       }
     };
 
-    let connection = connection.unwrap();
-
-    self.m_writer = Some(LineWriter::new(connection.try_clone().unwrap()));
-    self.m_reader = Some(BufReader::new(connection));
-
-    self.m_is_connected = true;
-
-    // read welcome message from server
-    info!("{}", self.read().unwrap());
-
-    Ok(())
+    match self.start_connection() {
+      Ok(()) => Ok(()),
+      Err(err) => {
+        error!("no welcome message received: {}", err);
+        self.fail_over()
+      }
+    }
   }
 
+  /// Logs in, fails over to the next server if the server does not answer the login. An
+  /// unverified login (e.g. with the default passcode `-1`) succeeds and allows receiving, only
+  /// uplink requires a verified login. An unexpected answer is not retried.
   pub fn login(&mut self, login_data: &LoginData) -> Result<(), std::io::Error> {
+    loop {
+      match self.try_login(login_data) {
+        Err(err) if err.kind() != std::io::ErrorKind::PermissionDenied => {
+          error!("login failed: {}", err);
+          self.fail_over()?;
+        }
+        result => return result,
+      }
+    }
+  }
+
+  fn try_login(&mut self, login_data: &LoginData) -> Result<(), std::io::Error> {
     info!("logging in with:\n{:#?}", &login_data);
 
-    let login_message = APRSClient::create_aprs_login(&login_data);
+    let login_message = Self::create_aprs_login(login_data);
 
     self.send_message(login_message.as_str())?;
-    let login_answer = self.read()?;
+    let login_answer = self.read()?.message;
     debug!("login answer:  {}", login_answer);
 
-    self.m_logged_in = parser::parse_login_answer(&login_answer);
+    if !login_answer.starts_with("# logresp") {
+      error!("...failed to log in, unexpected answer: {}", login_answer);
+      return Err(std::io::Error::new(
+        std::io::ErrorKind::PermissionDenied,
+        format!("unexpected login answer: {}", login_answer),
+      ));
+    }
 
-    match self.m_logged_in {
-      true => {
-        info!("...logged in successfully.");
-        self.m_user = Some(String::from(login_data.user_name));
-        Ok(())
-      }
-      false => {
-        error!("...failed to log in!");
-        Err(std::io::Error::new(
-          std::io::ErrorKind::PermissionDenied,
-          format!(
-            "could not log in with given credentials: {:#?}",
-            &login_data
-          ),
-        ))
-      }
+    self.m_logged_in = true;
+    self.m_verified = parser::parse_login_answer(&login_answer);
+    self.m_server = parser::parse_login_server(&login_answer);
+    self.m_user = Some(String::from(login_data.user_name));
+    self.m_login_data = Some(login_data.clone());
+    self.emit(ConnectionEvent::LoggedIn {
+      verified: self.m_verified,
+    });
+
+    match self.m_verified {
+      true => info!("...logged in successfully."),
+      false => warn!("...logged in unverified, uplink is not possible"),
     }
+
+    Ok(())
   }
 
   pub fn login_default(&mut self) -> Result<(), std::io::Error> {
@@ -140,110 +385,402 @@ impl APRSClient {
     self.login(&LoginData::new())
   }
 
+  /// The server answered the login, verified or not
   pub fn is_logged_in(&self) -> bool {
     self.m_logged_in
   }
 
-  pub fn run(this: Arc<Mutex<Self>>) {
+  /// The server verified the passcode of the login, required to send positions and status
+  pub fn is_verified(&self) -> bool {
+    self.m_verified
+  }
+
+  /// Starts the reader thread passing the received lines to the callback and reconnecting
+  /// according to the retry policy. The thread locks the client for one read or reconnect attempt
+  /// at a time and sleeps between the attempts without the lock.
+  pub fn run(this: Arc<Mutex<Self>>)
+  where
+    T: 'static,
+  {
     info!("starting the client reader thread...");
 
     //
     let mut lock = this.lock().unwrap();
     if !lock.m_is_connected {
       info!("currently not connected, trying to connect...");
-      lock.connect().unwrap();
+      // the reader thread keeps retrying according to the retry policy
+      if let Err(err) = lock.reconnect() {
+        error!("connecting failed: {}", err);
+      }
     }
 
     let clone = this.clone();
 
     lock.m_thread = Some(std::thread::spawn(move || {
-      // read the message and pass it to the callback
-      let mut lock = clone.lock().unwrap();
-
-      while !lock.m_terminate {
-        let message = lock.read().unwrap();
-        (lock.m_callback)(&message);
-        // std::thread::sleep(std::time::Duration::from_millis(10));
-        std::thread::sleep(std::time::Duration::from_secs(1));
+      // the client is locked for a single read or reconnect attempt at a time, so it can be
+      // used (e.g. to send positions) while the reader thread is running
+      let mut failed_attempts = 0;
+
+      loop {
+        let mut lock = clone.lock().unwrap();
+        if lock.m_terminate {
+          break;
+        }
+
+        if !lock.m_is_connected {
+          // the first attempt after losing the connection is immediate
+          let delay = match failed_attempts {
+            0 => std::time::Duration::ZERO,
+            _ => match lock.m_retry_policy.delay(failed_attempts) {
+              Some(delay) => delay,
+              None => {
+                error!("giving up after {} reconnect attempts", failed_attempts);
+                break;
+              }
+            },
+          };
+          lock.emit(ConnectionEvent::Reconnecting {
+            attempt: failed_attempts + 1,
+            delay,
+          });
+          drop(lock);
+          std::thread::sleep(delay);
+
+          let mut lock = clone.lock().unwrap();
+          if lock.m_terminate {
+            break;
+          }
+          match lock.reconnect() {
+            Ok(()) => failed_attempts = 0,
+            Err(err) => {
+              error!("reconnect failed: {}", err);
+              failed_attempts += 1;
+            }
+          }
+          continue;
+        }
+
+        match lock.read() {
+          Ok(message) => (lock.m_callback)(&message),
+          Err(err) => error!("{}", err),
+        }
+        if lock.is_keepalive_due() {
+          if let Err(err) = lock.send_heart_beat() {
+            error!("sending keepalive failed: {}", err);
+          }
+        }
+        drop(lock);
+
+        // give threads waiting for the client a chance to take the lock
+        std::thread::yield_now();
       }
     }));
   }
 
-  pub fn send_position(&mut self, _position: &str) -> Result<(), std::io::Error> {
-    // make sure we are logged in
-    if let None = self.m_user {
-      return Err(std::io::Error::new(
-        std::io::ErrorKind::PermissionDenied,
-        "permission denied",
-      ));
-    }
-
-    // get the timestamp
-    let now = Utc::now();
-    let mut time_string = String::new();
-    time_string.push_str(&now.minute().to_string());
-    time_string.push_str(&now.hour().to_string());
-    time_string.push_str(&now.second().to_string());
+  /// Sends the position report as OGN tracker APRS position line with the current time
+  pub fn send_position(&mut self, position: &PositionReport) -> Result<(), std::io::Error> {
+    self.check_uplink()?;
 
-    // convert time to wonky format
-    let position = "5123.45N/00123.45E";
-    let ground_track = 180;
-    let ground_speed = 25;
-    let altitude = 1000;
-
-    // general aprs message (i.e. before ogn extras)
     let position_message = format!(
-      "{}>OGNAPP:/{}h{}'{}/{}/A={}",
+      "{}>OGNAPP:{}",
       self.m_user.as_ref().unwrap(),
-      time_string,
-      position,
-      ground_track,
-      ground_speed,
-      altitude
+      position.to_body(Utc::now()).to_aprs_string()
     );
 
-    self.send_message(&position_message)
+    self.send_uplink(&position_message)?;
+    self.m_position_scheduler.sent_at(position, Instant::now());
+
+    Ok(())
   }
 
-  pub fn send_status(&self, status_message: &OgnStatusMessage) {
-    if self.is_logged_in() {
-      // TODO serialize status message and pass along the line
-      debug!("{:#?}", status_message);
-    } else {
-      error!("not logged in, cannot send status message!");
+  /// Sends the position report if it is due according to the position scheduler, meant to be
+  /// called with every position fix. Returns whether the report has been sent.
+  pub fn send_position_if_due(
+    &mut self,
+    position: &PositionReport,
+  ) -> Result<bool, std::io::Error> {
+    if !self.m_position_scheduler.is_due(position) {
+      return Ok(false);
     }
+
+    self.send_position(position)?;
+    Ok(true)
+  }
+
+  /// Sets the scheduler deciding when `send_position_if_due` sends a report
+  pub fn set_position_scheduler(&mut self, position_scheduler: PositionScheduler) {
+    self.m_position_scheduler = position_scheduler;
+  }
+
+  /// Limits the rate of positions and status messages sent to the server (default: bursts of 10
+  /// lines, one line per second), lines exceeding the limit are dropped with a `WouldBlock` error.
+  /// Login and filter lines are not limited. `None` disables the limit.
+  pub fn set_rate_limit(&mut self, rate_limiter: Option<TokenBucket>) {
+    self.m_rate_limiter = rate_limiter;
+  }
+
+  /// Sets the callback notified about changes of the connection state. Like the message callback
+  /// it is called with the client locked, so it must not access the client.
+  pub fn set_event_callback(&mut self, event_callback: EventCallback) {
+    self.m_event_callback = Some(event_callback);
+  }
+
+  /// The `run` loop sends a `#keepalive` comment when no line has been sent within the interval,
+  /// `None` (the default) sends no keepalives
+  pub fn set_keepalive_interval(&mut self, keepalive_interval: Option<std::time::Duration>) {
+    self.m_keepalive_interval = keepalive_interval;
+  }
+
+  /// Sets the login data used by `reconnect` without logging in
+  pub fn set_login_data(&mut self, login_data: LoginData) {
+    self.m_login_data = Some(login_data);
+  }
+
+  /// Sets the filter applied by `reconnect` after the login without sending it
+  pub fn set_filter_expression(&mut self, filter_expression: &str) {
+    self.m_filter = Some(filter_expression.to_string());
+  }
+
+  /// Longer lines are skipped with an `InvalidData` error (default: `MAX_LINE_LENGTH`)
+  pub fn set_max_line_length(&mut self, max_line_length: usize) {
+    self.m_max_line_length = max_line_length;
+  }
+
+  pub fn set_line_decoding(&mut self, line_decoding: LineDecoding) {
+    self.m_line_decoding = line_decoding;
+  }
+
+  /// Sets the delays between the reconnect attempts of the `run` loop, fails with `InvalidInput`
+  /// for a policy rejected by `RetryPolicy::validate`
+  pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) -> Result<(), std::io::Error> {
+    retry_policy.validate()?;
+    self.m_retry_policy = retry_policy;
+
+    Ok(())
+  }
+
+  /// Sends the status message as OGN tracker APRS status line with the current time
+  pub fn send_status(&mut self, status_message: &OgnStatusMessage) -> Result<(), std::io::Error> {
+    self.check_uplink()?;
+    status_message.validate()?;
+
+    let status_line = format!(
+      "{}>OGNAPP:>{}h {}",
+      self.m_user.as_ref().unwrap(),
+      Utc::now().format("%H%M%S"),
+      status_message.to_aprs_string()
+    );
+
+    self.send_uplink(&status_line)
   }
 
   pub fn set_filter(&mut self, filter_expression: &str) -> Result<(), std::io::Error> {
-    if self.m_port != PORT::FILTER {
-      error!("connected to fullfeed port, cannot set a filter");
+    if !self.m_port.capabilities().filter {
+      error!("port {} does not support filters, cannot set a filter", self.m_port.number());
       return Err(std::io::Error::new(
         std::io::ErrorKind::Unsupported,
-        "cannot set filter on fullfeed port",
+        "cannot set filter on a port without filter support",
       ));
     }
 
     debug!("applying filter: '{}'", filter_expression);
-    self.send_message(&format!("#filter {}", filter_expression))
+    self.send_message(&format!("#filter {}", filter_expression))?;
+    self.m_filter = Some(filter_expression.to_string());
+    self.emit(ConnectionEvent::FilterApplied {
+      filter: filter_expression.to_string(),
+    });
+
+    Ok(())
+  }
+
+  /// Connects again and restores the session, i.e. logs in with the last login data (verified or
+  /// not) and applies the last filter
+  pub fn reconnect(&mut self) -> Result<(), std::io::Error> {
+    info!("reconnecting...");
+    self.connect()?;
+
+    if let Some(login_data) = self.m_login_data.clone() {
+      self.login(&login_data)?;
+    }
+    if let Some(filter) = self.m_filter.clone() {
+      self.set_filter(&filter)?;
+    }
+
+    Ok(())
   }
 
   // ------------------------------------------------------------------------------
   // Private interface
   // ------------------------------------------------------------------------------
-  fn read(&mut self) -> Result<String, std::io::Error> {
-    let mut string_buffer = String::new();
+  /// Uplink requires a port accepting it and a verified login
+  fn check_uplink(&self) -> Result<(), std::io::Error> {
+    if !self.m_port.capabilities().uplink {
+      return Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        format!("port {} does not accept uplink", self.m_port.number()),
+      ));
+    }
 
-    //TODO add timeout and disconnect
+    if !self.m_verified || self.m_user.is_none() {
+      error!("not logged in verified, cannot send uplink");
+      return Err(std::io::Error::new(
+        std::io::ErrorKind::PermissionDenied,
+        "permission denied, uplink requires a verified login",
+      ));
+    }
 
-    self
-      .m_reader
-      .as_mut()
-      .unwrap()
-      .read_line(&mut string_buffer)?;
-    string_buffer = string_buffer.trim_end().to_string();
+    Ok(())
+  }
+
+  fn emit(&self, event: ConnectionEvent) {
+    if let Some(event_callback) = &self.m_event_callback {
+      event_callback(&event);
+    }
+  }
+
+  fn disconnect(&mut self, reason: &str) {
+    let _ = self.m_transport.close();
+    self.m_is_connected = false;
+    self.m_logged_in = false;
+    self.m_verified = false;
+    self.emit(ConnectionEvent::Disconnected {
+      reason: reason.to_string(),
+    });
+  }
+
+  /// Resets the connection state and reads the welcome message of a new connection
+  fn start_connection(&mut self) -> Result<(), std::io::Error> {
+    self.m_is_connected = true;
+    self.m_logged_in = false;
+    self.m_verified = false;
+    self.m_server = None;
+    self.m_banner = None;
+    self.m_connection_id += 1;
+    self.m_sequence = 0;
+
+    // read welcome message from server (outside of the log macro, which skips disabled levels)
+    let welcome = self.read()?;
+    info!("{}", welcome.message);
+    self.m_banner = ServerComment::parse(&welcome.message);
+    self.emit(ConnectionEvent::Connected {
+      server: self.m_transport.peer_address(),
+    });
+
+    Ok(())
+  }
+
+  /// Connects to the next servers until a welcome message is received
+  fn fail_over(&mut self) -> Result<(), std::io::Error> {
+    loop {
+      self.emit(ConnectionEvent::Connecting);
+      if let Err(err) = self.m_transport.connect_next() {
+        error!("fail over failed: {}", err);
+        self.m_is_connected = false;
+        return Err(err);
+      }
+
+      match self.start_connection() {
+        Ok(()) => return Ok(()),
+        Err(err) => error!("no welcome message received: {}", err),
+      }
+    }
+  }
+
+  fn create(transport: T, port: PORT, callback: Box<dyn Fn(&ReceivedMessage) + Send>) -> Self {
+    APRSClient {
+      m_port: port,
+      m_transport: transport,
+      m_callback: callback,
+      m_thread: None,
+      m_terminate: false,
+      m_logged_in: false,
+      m_verified: false,
+      m_user: None,
+      m_is_connected: false,
+      m_server: None,
+      m_connection_id: 0,
+      m_sequence: 0,
+      m_rate_limiter: Some(TokenBucket::default()),
+      m_position_scheduler: PositionScheduler::default(),
+      m_login_data: None,
+      m_filter: None,
+      m_retry_policy: RetryPolicy::default(),
+      m_event_callback: None,
+      m_max_line_length: MAX_LINE_LENGTH,
+      m_line_decoding: LineDecoding::default(),
+      m_banner: None,
+      m_clock_skew: None,
+      m_keepalive_interval: None,
+      m_last_sent: Instant::now(),
+    }
+  }
+
+  fn read(&mut self) -> Result<ReceivedMessage, std::io::Error> {
+    let mut buffer = Vec::new();
+
+    let length = match self.m_transport.read_line(&mut buffer, self.m_max_line_length) {
+      Ok(length) => length,
+      // the line has been skipped, the connection is still usable
+      Err(err) if err.kind() == std::io::ErrorKind::InvalidData => {
+        warn!("skipping line: {}", err);
+        return Err(err);
+      }
+      Err(err)
+        if err.kind() == std::io::ErrorKind::WouldBlock
+          || err.kind() == std::io::ErrorKind::TimedOut =>
+      {
+        error!("read timed out, disconnecting");
+        self.disconnect("read timed out, no keepalive received");
+        return Err(std::io::Error::new(
+          std::io::ErrorKind::TimedOut,
+          "read timed out, no keepalive received",
+        ));
+      }
+      Err(err) => {
+        self.disconnect(&err.to_string());
+        return Err(err);
+      }
+    };
+
+    if length == 0 {
+      info!("connection closed by the server");
+      self.disconnect("connection closed by the server");
+      return Err(std::io::Error::new(
+        std::io::ErrorKind::UnexpectedEof,
+        "connection closed by the server",
+      ));
+    }
+
+    let received_at = Instant::now();
+    let received_utc = Utc::now();
+
+    while let Some(b'\r' | b'\n') = buffer.last() {
+      buffer.pop();
+    }
+    let string_buffer = self.m_line_decoding.decode(&buffer).trim_end().to_string();
     debug!("read message: {}", string_buffer);
 
-    Ok(string_buffer)
+    if string_buffer.starts_with('#') {
+      let skew =
+        ServerComment::parse(&string_buffer).and_then(|comment| comment.clock_skew(received_utc));
+      if skew.is_some() {
+        self.m_clock_skew = skew;
+      }
+    }
+
+    let message = ReceivedMessage {
+      message: string_buffer,
+      raw: buffer,
+      length,
+      received_at,
+      received_utc,
+      server: self.m_server.clone(),
+      connection_id: self.m_connection_id,
+      sequence: self.m_sequence,
+    };
+    self.m_sequence += 1;
+
+    Ok(message)
   }
 
   fn send_message(&mut self, message: &str) -> Result<(), std::io::Error> {
@@ -256,24 +793,39 @@ impl APRSClient {
       ));
     }
 
-    let mut full_message = String::new();
     debug!("sending message: '{}'", message);
-
-    full_message.push_str(message);
-    full_message.push_str("\r\n");
-    self
-      .m_writer
-      .as_mut()
-      .unwrap()
-      .write_all(full_message.as_bytes())?;
-    self.m_writer.as_mut().unwrap().flush()?;
+    self.m_transport.write_line(message)?;
+    self.m_last_sent = Instant::now();
 
     Ok(())
   }
 
-  // fn send_heart_beat(&mut self) {
-  //   self.send_message("#keepalive").unwrap();
-  // }
+  /// Sends a position or status line subject to the rate limit, session lines (login, filter)
+  /// are sent with `send_message` and never dropped
+  fn send_uplink(&mut self, message: &str) -> Result<(), std::io::Error> {
+    if let Some(rate_limiter) = self.m_rate_limiter.as_mut() {
+      if !rate_limiter.try_acquire() {
+        error!("rate limit exceeded, dropping message: '{}'", message);
+        return Err(std::io::Error::new(
+          std::io::ErrorKind::WouldBlock,
+          "rate limit exceeded, message dropped",
+        ));
+      }
+    }
+
+    self.send_message(message)
+  }
+
+  fn is_keepalive_due(&self) -> bool {
+    match self.m_keepalive_interval {
+      Some(interval) => self.m_is_connected && self.m_last_sent.elapsed() >= interval,
+      None => false,
+    }
+  }
+
+  fn send_heart_beat(&mut self) -> Result<(), std::io::Error> {
+    self.send_message("#keepalive")
+  }
 
   fn create_aprs_login(login_data: &LoginData) -> String {
     format!(
@@ -283,15 +835,16 @@ impl APRSClient {
   }
 }
 
-impl Drop for APRSClient {
+impl<T: Transport> Drop for APRSClient<T> {
   fn drop(&mut self) {
     info!("...terminating the aprs client!");
 
     self.m_terminate = true;
+    let _ = self.m_transport.close();
   }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct LoginData {
   pub user_name: &'static str,
   pub pass_code: &'static str,
@@ -334,7 +887,99 @@ impl LoginData {
   }
 }
 
-#[derive(Debug, PartialEq)]
+impl Default for LoginData {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+/// Position of an aircraft to be sent with `APRSClient::send_position`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PositionReport {
+  pub latitude: f64, // [degrees]
+  pub longitude: f64, // [degrees]
+  pub ground_track: u16, // [degrees]
+  pub ground_speed: Knots,
+  pub altitude: Feet,
+  pub climb_rate: FeetPerMinute,
+  pub turn_rate: HalfTurnsPerMinute,
+  pub aircraft_id: OgnId,
+}
+
+impl PositionReport {
+  pub fn new(latitude: f64, longitude: f64, aircraft_id: OgnId) -> PositionReport {
+    Self {
+      latitude,
+      longitude,
+      ground_track: 0,
+      ground_speed: Knots(0.0),
+      altitude: Feet(0),
+      climb_rate: FeetPerMinute(0.0),
+      turn_rate: HalfTurnsPerMinute(0.0),
+      aircraft_id,
+    }
+  }
+
+  pub fn ground_track(mut self, ground_track: u16) -> PositionReport {
+    self.ground_track = ground_track % 360;
+    self
+  }
+
+  pub fn ground_speed(mut self, ground_speed: Knots) -> PositionReport {
+    self.ground_speed = ground_speed;
+    self
+  }
+
+  pub fn altitude(mut self, altitude: Feet) -> PositionReport {
+    self.altitude = altitude;
+    self
+  }
+
+  pub fn climb_rate(mut self, climb_rate: FeetPerMinute) -> PositionReport {
+    self.climb_rate = climb_rate;
+    self
+  }
+
+  pub fn turn_rate(mut self, turn_rate: HalfTurnsPerMinute) -> PositionReport {
+    self.turn_rate = turn_rate;
+    self
+  }
+
+  /// Creates the body of the position message, the symbol is derived from the aircraft type
+  pub fn to_body(&self, timestamp: DateTime<Utc>) -> OgnBody {
+    let (symbol_table, symbol_code) = self.aircraft_id.aircraft_type.symbol();
+
+    OgnBody {
+      timestamp,
+      position: Coordinate {
+        x: self.latitude,
+        y: self.longitude,
+      },
+      precision_enhancement: true,
+      symbol_table,
+      symbol_code,
+      ground_speed: Some(self.ground_speed),
+      ground_turning_rate: Some(self.turn_rate),
+      climb_rate: Some(self.climb_rate),
+      altitude: Some(self.altitude),
+      ground_track: Some(self.ground_track),
+      gps_accuracy: None,
+      signal_strength: None,
+      id: Some(self.aircraft_id.to_aprs_string()),
+      mic_e: None,
+    }
+  }
+}
+
+/// Maximum length of a status message value, the OGN tracker stores each value in 16 bytes
+/// including the terminating zero
+pub const MAX_STATUS_FIELD_LENGTH: usize = 15;
+
+/// Static information of an OGN tracker (pilot, aircraft, competition, ...), sent as APRS status
+/// line. Use `OgnStatusMessage::builder()` to create a validated message.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OgnStatusMessage {
   pilot_name: Option<String>,
   manufacturer: Option<String>,
@@ -352,95 +997,782 @@ pub struct OgnStatusMessage {
 }
 
 impl OgnStatusMessage {
+  /// An empty status message, use `builder()` to set the values
   pub fn new() -> Self {
-    Self {
-      pilot_name: None,
-      manufacturer: None,
-      model: None,
-      make: None,
-      serial_number: None,
-      competition_id: None,
-      competition_class: None,
-      competition_task: None,
-      base_airfield: None,
-      in_case_of_emergency: None,
-      pilot_id: None,
-      hardware: None,
-      software: None,
-    }
+    Self::default()
+  }
+
+  pub fn builder() -> OgnStatusMessageBuilder {
+    OgnStatusMessageBuilder::default()
   }
 
+  pub fn get_pilot_name(&self) -> Option<&str> {
+    self.pilot_name.as_deref()
+  }
+
+  pub fn get_manufacturer(&self) -> Option<&str> {
+    self.manufacturer.as_deref()
+  }
+
+  pub fn get_model(&self) -> Option<&str> {
+    self.model.as_deref()
+  }
+
+  pub fn get_make(&self) -> Option<&str> {
+    self.make.as_deref()
+  }
+
+  pub fn get_serial_number(&self) -> Option<&str> {
+    self.serial_number.as_deref()
+  }
+
+  pub fn get_competition_id(&self) -> Option<&str> {
+    self.competition_id.as_deref()
+  }
+
+  pub fn get_competition_class(&self) -> Option<&str> {
+    self.competition_class.as_deref()
+  }
+
+  pub fn get_competition_task(&self) -> Option<&str> {
+    self.competition_task.as_deref()
+  }
+
+  pub fn get_base_airfield(&self) -> Option<&str> {
+    self.base_airfield.as_deref()
+  }
+
+  pub fn get_in_case_of_emergency(&self) -> Option<&str> {
+    self.in_case_of_emergency.as_deref()
+  }
+
+  pub fn get_pilot_id(&self) -> Option<&str> {
+    self.pilot_id.as_deref()
+  }
+
+  pub fn get_hardware(&self) -> Option<&str> {
+    self.hardware.as_deref()
+  }
+
+  pub fn get_software(&self) -> Option<&str> {
+    self.software.as_deref()
+  }
+
+  #[deprecated(note = "use OgnStatusMessage::builder()")]
   pub fn pilot_name(&mut self, pilot_name: &str) -> &mut Self {
     self.pilot_name = Some(String::from(pilot_name));
     self
   }
 
+  #[deprecated(note = "use OgnStatusMessage::builder()")]
   pub fn manufacturer(&mut self, manufacturer: &str) -> &mut Self {
     self.manufacturer = Some(String::from(manufacturer));
     self
   }
 
+  #[deprecated(note = "use OgnStatusMessage::builder()")]
   pub fn model(mut self, model: &str) -> Self {
     self.model = Some(String::from(model));
     self
   }
+
+  #[deprecated(note = "use OgnStatusMessage::builder()")]
   pub fn make(mut self, make: &str) -> Self {
     self.make = Some(String::from(make));
     self
   }
+
+  #[deprecated(note = "use OgnStatusMessage::builder()")]
   pub fn serial_number(mut self, serial_number: &str) -> Self {
     self.serial_number = Some(String::from(serial_number));
     self
   }
+
+  #[deprecated(note = "use OgnStatusMessage::builder()")]
   pub fn competition_id(mut self, competition_id: &str) -> Self {
     self.competition_id = Some(String::from(competition_id));
     self
   }
+
+  #[deprecated(note = "use OgnStatusMessage::builder()")]
   pub fn competition_class(mut self, competition_class: &str) -> Self {
     self.competition_class = Some(String::from(competition_class));
     self
   }
+
+  #[deprecated(note = "use OgnStatusMessage::builder()")]
   pub fn competition_task(mut self, competition_task: &str) -> Self {
     self.competition_task = Some(String::from(competition_task));
     self
   }
+
+  #[deprecated(note = "use OgnStatusMessage::builder()")]
   pub fn base_airfield(mut self, base_airfield: &str) -> Self {
     self.base_airfield = Some(String::from(base_airfield));
     self
   }
+
+  #[deprecated(note = "use OgnStatusMessage::builder()")]
   pub fn in_case_of_emergency(mut self, in_case_of_emergency: &str) -> Self {
     self.in_case_of_emergency = Some(String::from(in_case_of_emergency));
     self
   }
+
+  #[deprecated(note = "use OgnStatusMessage::builder()")]
   pub fn pilot_id(mut self, pilot_id: &str) -> Self {
     self.pilot_id = Some(String::from(pilot_id));
     self
   }
+
+  #[deprecated(note = "use OgnStatusMessage::builder()")]
   pub fn hardware(mut self, hardware: &str) -> Self {
     self.hardware = Some(String::from(hardware));
     self
   }
+
+  #[deprecated(note = "use OgnStatusMessage::builder()")]
   pub fn software(mut self, software: &str) -> Self {
     self.software = Some(String::from(software));
     self
   }
+
+  /// Checks the values against the OGN tracker limits: printable ASCII with at most
+  /// `MAX_STATUS_FIELD_LENGTH` characters. `_` is not allowed, the status line encodes spaces as
+  /// `_` and could not tell them apart.
+  pub fn validate(&self) -> Result<(), std::io::Error> {
+    for (key, value) in self.fields().iter() {
+      if let Some(value) = value {
+        if value.is_empty() || value.len() > MAX_STATUS_FIELD_LENGTH {
+          return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!(
+              "status value {} must have 1 to {} characters: '{}'",
+              key, MAX_STATUS_FIELD_LENGTH, value
+            ),
+          ));
+        }
+
+        if !value.chars().all(|c| c == ' ' || c.is_ascii_graphic()) {
+          return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("status value {} must be printable ascii: '{}'", key, value),
+          ));
+        }
+
+        if value.contains('_') {
+          return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("status value {} must not contain '_', it encodes spaces: '{}'", key, value),
+          ));
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  /// The fields with the keys of the OGN tracker status line
+  pub(crate) fn fields(&self) -> [(&'static str, &Option<String>); 13] {
+    [
+      ("Pilot", &self.pilot_name),
+      ("Manuf", &self.manufacturer),
+      ("Model", &self.model),
+      ("Type", &self.make),
+      ("SN", &self.serial_number),
+      ("ID", &self.competition_id),
+      ("Class", &self.competition_class),
+      ("Task", &self.competition_task),
+      ("Base", &self.base_airfield),
+      ("ICE", &self.in_case_of_emergency),
+      ("PilotID", &self.pilot_id),
+      ("Hard", &self.hardware),
+      ("Soft", &self.software),
+    ]
+  }
+
+  pub(crate) fn field_mut(&mut self, key: &str) -> Option<&mut Option<String>> {
+    match key {
+      "Pilot" => Some(&mut self.pilot_name),
+      "Manuf" => Some(&mut self.manufacturer),
+      "Model" => Some(&mut self.model),
+      "Type" => Some(&mut self.make),
+      "SN" => Some(&mut self.serial_number),
+      "ID" => Some(&mut self.competition_id),
+      "Class" => Some(&mut self.competition_class),
+      "Task" => Some(&mut self.competition_task),
+      "Base" => Some(&mut self.base_airfield),
+      "ICE" => Some(&mut self.in_case_of_emergency),
+      "PilotID" => Some(&mut self.pilot_id),
+      "Hard" => Some(&mut self.hardware),
+      "Soft" => Some(&mut self.software),
+      _ => None,
+    }
+  }
 }
 
-// {
-//   "/z",  //  0 = ?
-//   "/'",  //  1 = (moto-)glider    (most frequent)
-//   "/'",  //  2 = tow plane        (often)
-//   "/X",  //  3 = helicopter       (often)
-//   "/g" , //  4 = parachute        (rare but seen - often mixed with drop plane)
-//   "\\^", //  5 = drop plane       (seen)
-//   "/g" , //  6 = hang-glider      (rare but seen)
-//   "/g" , //  7 = para-glider      (rare but seen)
-//   "\\^", //  8 = powered aircraft (often)
-//   "/^",  //  9 = jet aircraft     (rare but seen)
-//   "/z",  //  A = UFO              (people set for fun)
-//   "/O",  //  B = balloon          (seen once)
-//   "/O",  //  C = airship          (seen once)
-//   "/'",  //  D = UAV              (drones, can become very common)
-//   "/z",  //  E = ground support   (ground vehicles at airfields)
-//   "\\n"  //  F = static object    (ground relay ?)
-// } ;
+#[derive(Debug, Clone, Default)]
+pub struct OgnStatusMessageBuilder {
+  status_message: OgnStatusMessage,
+}
+
+impl OgnStatusMessageBuilder {
+  pub fn pilot_name(mut self, pilot_name: &str) -> OgnStatusMessageBuilder {
+    self.status_message.pilot_name = Some(String::from(pilot_name));
+    self
+  }
+
+  pub fn manufacturer(mut self, manufacturer: &str) -> OgnStatusMessageBuilder {
+    self.status_message.manufacturer = Some(String::from(manufacturer));
+    self
+  }
+
+  pub fn model(mut self, model: &str) -> OgnStatusMessageBuilder {
+    self.status_message.model = Some(String::from(model));
+    self
+  }
+
+  pub fn make(mut self, make: &str) -> OgnStatusMessageBuilder {
+    self.status_message.make = Some(String::from(make));
+    self
+  }
+
+  pub fn serial_number(mut self, serial_number: &str) -> OgnStatusMessageBuilder {
+    self.status_message.serial_number = Some(String::from(serial_number));
+    self
+  }
+
+  pub fn competition_id(mut self, competition_id: &str) -> OgnStatusMessageBuilder {
+    self.status_message.competition_id = Some(String::from(competition_id));
+    self
+  }
+
+  pub fn competition_class(mut self, competition_class: &str) -> OgnStatusMessageBuilder {
+    self.status_message.competition_class = Some(String::from(competition_class));
+    self
+  }
+
+  pub fn competition_task(mut self, competition_task: &str) -> OgnStatusMessageBuilder {
+    self.status_message.competition_task = Some(String::from(competition_task));
+    self
+  }
+
+  pub fn base_airfield(mut self, base_airfield: &str) -> OgnStatusMessageBuilder {
+    self.status_message.base_airfield = Some(String::from(base_airfield));
+    self
+  }
+
+  pub fn in_case_of_emergency(mut self, in_case_of_emergency: &str) -> OgnStatusMessageBuilder {
+    self.status_message.in_case_of_emergency = Some(String::from(in_case_of_emergency));
+    self
+  }
+
+  pub fn pilot_id(mut self, pilot_id: &str) -> OgnStatusMessageBuilder {
+    self.status_message.pilot_id = Some(String::from(pilot_id));
+    self
+  }
+
+  pub fn hardware(mut self, hardware: &str) -> OgnStatusMessageBuilder {
+    self.status_message.hardware = Some(String::from(hardware));
+    self
+  }
+
+  pub fn software(mut self, software: &str) -> OgnStatusMessageBuilder {
+    self.status_message.software = Some(String::from(software));
+    self
+  }
+
+  /// Validates the values, see `OgnStatusMessage::validate`
+  pub fn build(self) -> Result<OgnStatusMessage, std::io::Error> {
+    self.status_message.validate()?;
+    Ok(self.status_message)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use aircraft::{AddressType, AircraftType};
+  use chrono::TimeZone;
+  use std::time::Duration;
+
+  #[test]
+  fn position_report_test() {
+    let report = PositionReport::new(
+      51.0 + 45.953 / 60.0,
+      -(1.0 + 11.503 / 60.0),
+      OgnId::new(0x82149C, AddressType::Ogn, AircraftType::Glider),
+    )
+    .ground_track(232)
+    .ground_speed(Knots(25.0))
+    .altitude(Feet(2950))
+    .climb_rate(FeetPerMinute(-120.0))
+    .turn_rate(HalfTurnsPerMinute(-4.3));
+
+    let body = report.to_body(Utc.ymd(2021, 6, 1).and_hms(9, 5, 7));
+
+    assert_eq!(
+      body.to_aprs_string(),
+      "/090507h5145.95N/00111.50W'232/025/A=002950 !W33! id0782149C -120fpm -4.3rot"
+    );
+  }
+
+  #[test]
+  fn status_message_builder_test() {
+    let status = OgnStatusMessage::builder()
+      .pilot_name("John Smith")
+      .competition_id("JS")
+      .build()
+      .unwrap();
+
+    assert_eq!(status.get_pilot_name(), Some("John Smith"));
+    assert_eq!(status.get_competition_id(), Some("JS"));
+    assert_eq!(status.get_manufacturer(), None);
+    assert_eq!(OgnStatusMessage::builder().build().unwrap(), OgnStatusMessage::new());
+
+    let error = OgnStatusMessage::builder()
+      .pilot_name("Maximilian Mustermann")
+      .build()
+      .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+
+    assert!(OgnStatusMessage::builder().base_airfield("").build().is_err());
+    assert!(OgnStatusMessage::builder().base_airfield("Zürich").build().is_err());
+    assert!(OgnStatusMessage::builder().base_airfield("LSZH_West").build().is_err());
+  }
+
+  #[test]
+  #[allow(deprecated)]
+  fn deprecated_status_setters_test() {
+    let mut status = OgnStatusMessage::new().model("Discus 2c").competition_id("JS");
+    status.pilot_name("John Smith").manufacturer("Schempp-Hirth");
+
+    let expected = OgnStatusMessage::builder()
+      .pilot_name("John Smith")
+      .manufacturer("Schempp-Hirth")
+      .model("Discus 2c")
+      .competition_id("JS")
+      .build()
+      .unwrap();
+    assert_eq!(status, expected);
+  }
+
+  #[test]
+  fn client_memory_transport_test() {
+    let transport = transport::MemoryTransport::new();
+    let client = APRSClient::with_transport(transport.clone(), PORT::FILTER, Box::new(|_| {}));
+    let mut client = client.lock().unwrap();
+
+    assert!(!client.is_connected());
+    assert!(client.set_filter("r/47/7/100").is_err());
+
+    transport.push_line("# aprsc 2.1.10-gd72a17c");
+    transport.push_line("# logresp N0CALL verified, server GLIDERN1");
+    client.connect().unwrap();
+    client.login(&LoginData::new().user_name("N0CALL")).unwrap();
+    assert_eq!(client.server(), Some("GLIDERN1"));
+
+    client.set_filter("r/47/7/100").unwrap();
+    transport.push_line("LSTB>APRS,TCPIP*,qAC,GLIDERN2:/060854h4656.38NI00723.46E&/A=001877");
+    let message = client.read().unwrap();
+    assert_eq!(message.sequence, 2);
+    assert_eq!(message.connection_id, 1);
+    assert!(message.message.starts_with("LSTB>APRS"));
+
+    // the end of the stream is no message
+    transport.disconnect();
+    assert_eq!(client.read().unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+    assert_eq!(client.m_sequence, 3);
+    assert!(!client.is_connected());
+
+    assert_eq!(
+      transport.written_lines(),
+      vec![
+        "user N0CALL pass -1 vers ogn_client_rs 0.1.0".to_string(),
+        "#filter r/47/7/100".to_string(),
+      ]
+    );
+  }
+
+  #[test]
+  fn port_capabilities_test() {
+    assert_eq!(PORT::FILTER.number(), 14580);
+    assert_eq!(PORT::FULLFEED.number(), 10152);
+
+    let receive_only = PortCapabilities { filter: false, full_feed: true, uplink: false };
+    let port = PORT::Custom(20152, receive_only);
+    assert_eq!(port.number(), 20152);
+
+    let transport = transport::MemoryTransport::new();
+    let client = APRSClient::with_transport(transport.clone(), port, Box::new(|_| {}));
+    let mut client = client.lock().unwrap();
+
+    transport.push_line("# aprsc 2.1.10-gd72a17c");
+    transport.push_line("# logresp N0CALL verified, server GLIDERN1");
+    client.connect().unwrap();
+    client.login(&LoginData::new().user_name("N0CALL")).unwrap();
+
+    let error = client.set_filter("r/47/7/100").unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::Unsupported);
+    let position = PositionReport::new(
+      47.0,
+      7.0,
+      OgnId::new(0x123456, AddressType::Ogn, AircraftType::Glider),
+    );
+    let error = client.send_position(&position).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::Unsupported);
+    assert_eq!(transport.written_lines().len(), 1);
+  }
+
+  #[test]
+  fn rate_limit_test() {
+    let transport = transport::MemoryTransport::new();
+    let client = APRSClient::with_transport(transport.clone(), PORT::FILTER, Box::new(|_| {}));
+    let mut client = client.lock().unwrap();
+    client.set_rate_limit(Some(TokenBucket::new(1, 0.0)));
+
+    transport.push_line("# aprsc 2.1.10-gd72a17c");
+    transport.push_line("# logresp N0CALL verified, server GLIDERN1");
+    client.connect().unwrap();
+    client.login(&LoginData::new().user_name("N0CALL")).unwrap();
+
+    let position = PositionReport::new(
+      47.0,
+      7.0,
+      OgnId::new(0x123456, AddressType::Ogn, AircraftType::Glider),
+    );
+    client.send_position(&position).unwrap();
+    let error = client.send_position(&position).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::WouldBlock);
+
+    // session lines are not limited
+    transport.push_line("# logresp N0CALL verified, server GLIDERN1");
+    client.login(&LoginData::new().user_name("N0CALL")).unwrap();
+    client.set_filter("r/47/7/100").unwrap();
+    assert_eq!(transport.written_lines().len(), 4);
+  }
+
+  #[test]
+  fn connection_event_test() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let recorded = events.clone();
+
+    let transport = transport::MemoryTransport::new();
+    let client = APRSClient::with_transport(transport.clone(), PORT::FILTER, Box::new(|_| {}));
+    let mut client = client.lock().unwrap();
+    client.set_event_callback(Box::new(move |event| recorded.lock().unwrap().push(event.clone())));
+
+    transport.push_line("# aprsc 2.1.10-gd72a17c");
+    transport.push_line("# logresp N0CALL verified, server GLIDERN1");
+    client.connect().unwrap();
+    client.login(&LoginData::new().user_name("N0CALL")).unwrap();
+    client.set_filter("r/47/7/100").unwrap();
+    transport.disconnect();
+    assert_eq!(client.read().unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+
+    assert_eq!(
+      *events.lock().unwrap(),
+      vec![
+        ConnectionEvent::Connecting,
+        ConnectionEvent::Connected { server: None },
+        ConnectionEvent::LoggedIn { verified: true },
+        ConnectionEvent::FilterApplied {
+          filter: "r/47/7/100".to_string()
+        },
+        ConnectionEvent::Disconnected {
+          reason: "connection closed by the server".to_string()
+        },
+      ]
+    );
+  }
+
+  #[test]
+  fn read_invalid_bytes_test() {
+    let transport = transport::MemoryTransport::new();
+    let client = APRSClient::with_transport(transport.clone(), PORT::FILTER, Box::new(|_| {}));
+    let mut client = client.lock().unwrap();
+
+    transport.push_line("# aprsc 2.1.10-gd72a17c");
+    client.connect().unwrap();
+    client.set_max_line_length(64);
+
+    transport.push_bytes(b"FLRDDA5BA>OGFLR,qAS,LFMX:>Z\xfcrich \xff");
+    let message = client.read().unwrap();
+    assert_eq!(message.message, "FLRDDA5BA>OGFLR,qAS,LFMX:>Zürich ÿ");
+    assert_eq!(message.raw, b"FLRDDA5BA>OGFLR,qAS,LFMX:>Z\xfcrich \xff");
+
+    client.set_line_decoding(LineDecoding::Utf8Lossy);
+    transport.push_bytes(b"Z\xfcrich");
+    assert_eq!(client.read().unwrap().message, "Z\u{fffd}rich");
+
+    // too long lines are skipped without disconnecting
+    transport.push_line(&"x".repeat(100));
+    transport.push_line("# aprsc 2.1.10-gd72a17c");
+    let error = client.read().unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert!(client.is_connected());
+    assert_eq!(client.read().unwrap().message, "# aprsc 2.1.10-gd72a17c");
+  }
+
+  #[test]
+  fn banner_and_clock_skew_test() {
+    let transport = transport::MemoryTransport::new();
+    let client = APRSClient::with_transport(transport.clone(), PORT::FILTER, Box::new(|_| {}));
+    let mut client = client.lock().unwrap();
+
+    transport.push_line("# aprsc 2.1.10-gd72a17c");
+    client.connect().unwrap();
+    assert_eq!(client.banner().unwrap().software.as_deref(), Some("aprsc"));
+    assert_eq!(client.clock_skew(), None);
+
+    let server_time = Utc::now() - chrono::Duration::seconds(30);
+    transport.push_line(&format!(
+      "# aprsc 2.1.10-gd72a17c {} GMT GLIDERN3 1.2.3.4:14580",
+      server_time.format("%d %b %Y %H:%M:%S")
+    ));
+    client.read().unwrap();
+    let skew = client.clock_skew().unwrap().num_seconds();
+    assert!((30..=32).contains(&skew), "clock skew {}", skew);
+  }
+
+  fn mock_client(server: &test_server::MockServer) -> Arc<Mutex<APRSClient>> {
+    let transport = TcpTransport::new("127.0.0.1", server.port());
+    let client = APRSClient::with_transport(transport, PORT::FILTER, Box::new(|_| {}));
+
+    {
+      let mut locked_client = client.lock().unwrap();
+      locked_client.connect().unwrap();
+      locked_client
+        .login(&LoginData::new().user_name("N0CALL").pass_code("13023"))
+        .unwrap();
+    }
+
+    client
+  }
+
+  #[test]
+  fn mock_server_login_and_filter_test() {
+    let server = test_server::MockServer::start(PORT::FILTER).unwrap();
+    let client = mock_client(&server);
+    let mut client = client.lock().unwrap();
+
+    assert_eq!(client.server(), Some("TESTSRV"));
+    let logins = server.logins();
+    assert_eq!(logins.len(), 1);
+    assert!(logins[0].verified);
+    assert_eq!(logins[0].app_name, "ogn_client_rs");
+
+    client.set_filter("r/47/7/100").unwrap();
+    assert!(server.wait_until(Duration::from_secs(5), |server| server.filters().len() == 1));
+
+    // only traffic within the filter range is delivered
+    server.send_line("OGN82149C>OGNTRK,qAS,OxfBarton:/130208h5145.95N/00111.50W'232/000/A=000295");
+    server.send_line("LSTB>APRS,TCPIP*,qAC,GLIDERN2:/060854h4656.38NI00723.46E&/A=001877");
+
+    let message = client.read().unwrap();
+    assert!(message.message.starts_with("LSTB>APRS"));
+    assert_eq!(message.server.as_deref(), Some("TESTSRV"));
+  }
+
+  #[test]
+  fn mock_server_uplink_test() {
+    let server = test_server::MockServer::start(PORT::FILTER).unwrap();
+    let client = mock_client(&server);
+
+    let report = PositionReport::new(
+      47.0,
+      7.0,
+      OgnId::new(0x82149C, AddressType::Ogn, AircraftType::Glider),
+    );
+    client.lock().unwrap().send_position(&report).unwrap();
+
+    assert!(server.wait_until(Duration::from_secs(5), |server| server.uplinked_lines().len() == 1));
+    assert!(server.uplinked_lines()[0].starts_with("N0CALL>OGNAPP:/"));
+  }
+
+  #[test]
+  fn mock_server_run_and_uplink_test() {
+    let server =
+      test_server::MockServer::start_with_keepalive(PORT::FILTER, Duration::from_millis(50)).unwrap();
+    let client = mock_client(&server);
+    APRSClient::run(client.clone());
+
+    // the reader thread must not keep the client locked
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let uplink_client = client.clone();
+    std::thread::spawn(move || {
+      let report = PositionReport::new(
+        47.0,
+        7.0,
+        OgnId::new(0x82149C, AddressType::Ogn, AircraftType::Glider),
+      );
+      let result = uplink_client.lock().unwrap().send_position(&report);
+      sender.send(result.map_err(|err| err.kind())).unwrap();
+    });
+
+    assert_eq!(receiver.recv_timeout(Duration::from_secs(5)), Ok(Ok(())));
+    assert!(server.wait_until(Duration::from_secs(5), |server| server.uplinked_lines().len() == 1));
+    assert!(client.lock().unwrap().is_connected());
+
+    client.lock().unwrap().m_terminate = true;
+  }
+
+  #[test]
+  fn mock_server_reconnect_test() {
+    let server = test_server::MockServer::start(PORT::FULLFEED).unwrap();
+    let client = mock_client(&server);
+    let mut client = client.lock().unwrap();
+
+    server.disconnect_all();
+    assert_eq!(client.read().unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+
+    client.connect().unwrap();
+    client.login(&LoginData::new()).unwrap();
+    assert!(client.is_logged_in());
+    assert!(!client.is_verified());
+    assert_eq!(server.connection_count(), 2);
+    assert!(!server.logins()[1].verified);
+
+    server.send_line("LSTB>APRS,TCPIP*,qAC,GLIDERN2:/060854h4656.38NI00723.46E&/A=001877");
+    let message = client.read().unwrap();
+    assert_eq!(message.connection_id, 2);
+    assert_eq!(message.sequence, 2);
+  }
+
+  #[test]
+  fn mock_server_keepalive_test() {
+    let server =
+      test_server::MockServer::start_with_keepalive(PORT::FILTER, Duration::from_millis(50)).unwrap();
+    let client = mock_client(&server);
+
+    let mut client = client.lock().unwrap();
+    let message = client.read().unwrap();
+    assert!(message.message.starts_with("# aprsc 2.1.10-test"));
+    assert!(message.message.ends_with(&format!("TESTSRV 127.0.0.1:{}", server.port())));
+    let comment = parser::ServerComment::parse(&message.message).unwrap();
+    assert_eq!(comment.server_name.as_deref(), Some("TESTSRV"));
+    assert_eq!(comment.address, Some(([127, 0, 0, 1], server.port()).into()));
+    assert!(client.clock_skew().unwrap().num_seconds().abs() <= 1);
+  }
+
+  #[test]
+  fn mock_server_failover_test() {
+    // accepts a single connection and drops it on the login
+    let broken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let broken_port = broken.local_addr().unwrap().port();
+    let broken_server = std::thread::spawn(move || {
+      let (mut stream, _) = broken.accept().unwrap();
+      std::io::Write::write_all(&mut stream, b"# aprsc 2.1.10-broken\r\n").unwrap();
+      let mut line = String::new();
+      std::io::BufRead::read_line(&mut std::io::BufReader::new(&stream), &mut line).unwrap();
+    });
+
+    let server = test_server::MockServer::start(PORT::FILTER).unwrap();
+    let servers = [
+      format!("127.0.0.1:{}", broken_port),
+      format!("127.0.0.1:{}", server.port()),
+    ];
+    let client = APRSClient::with_servers(
+      &[servers[0].as_str(), servers[1].as_str()],
+      PORT::FILTER,
+      Box::new(|_| {}),
+    );
+    let mut client = client.lock().unwrap();
+    assert_eq!(client.peer_address().unwrap().port(), broken_port);
+
+    client
+      .login(&LoginData::new().user_name("N0CALL").pass_code("13023"))
+      .unwrap();
+    broken_server.join().unwrap();
+
+    assert_eq!(client.peer_address().unwrap().port(), server.port());
+    assert_eq!(client.server(), Some("TESTSRV"));
+    assert!(client.is_logged_in());
+    assert_eq!(server.logins().len(), 1);
+
+    // an unverified login is answered, so it is not retried on another server
+    client.connect().unwrap();
+    assert_eq!(client.peer_address().unwrap().port(), server.port());
+    client.login(&LoginData::new()).unwrap();
+    assert!(!client.is_verified());
+    assert_eq!(server.connection_count(), 2);
+  }
+
+  #[test]
+  fn read_timeout_and_reconnect_test() {
+    let server = test_server::MockServer::start(PORT::FILTER).unwrap();
+    let timeouts = connection::Timeouts {
+      read: Some(Duration::from_millis(200)),
+      ..connection::Timeouts::default()
+    };
+    let transport = TcpTransport::new("127.0.0.1", server.port()).timeouts(timeouts);
+    let client = APRSClient::with_transport(transport, PORT::FILTER, Box::new(|_| {}));
+    let mut client = client.lock().unwrap();
+
+    client.connect().unwrap();
+    client
+      .login(&LoginData::new().user_name("N0CALL").pass_code("13023"))
+      .unwrap();
+    client.set_filter("r/47/7/100").unwrap();
+
+    // no keepalive within the read timeout
+    let error = client.read().unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::TimedOut);
+    assert!(!client.is_connected());
+    assert!(!client.is_logged_in());
+
+    // the session is restored
+    client.reconnect().unwrap();
+    assert!(client.is_logged_in());
+    assert_eq!(server.logins().len(), 2);
+    assert!(server.wait_until(Duration::from_secs(5), |server| server.filters().len() == 2));
+  }
+
+  #[test]
+  fn mock_server_unverified_reconnect_test() {
+    let server = test_server::MockServer::start(PORT::FILTER).unwrap();
+    let transport = TcpTransport::new("127.0.0.1", server.port());
+    let client = APRSClient::with_transport(transport, PORT::FILTER, Box::new(|_| {}));
+    let mut client = client.lock().unwrap();
+
+    // receive only login with the default passcode -1
+    client.connect().unwrap();
+    client.login(&LoginData::new()).unwrap();
+    assert!(client.is_logged_in());
+    assert!(!client.is_verified());
+    client.set_filter("r/47/7/100").unwrap();
+
+    let position = PositionReport::new(
+      47.0,
+      7.0,
+      OgnId::new(0x82149C, AddressType::Ogn, AircraftType::Glider),
+    );
+    let error = client.send_position(&position).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::PermissionDenied);
+
+    server.disconnect_all();
+    assert_eq!(client.read().unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+
+    // the filter is restored after the unverified login
+    client.reconnect().unwrap();
+    assert!(client.is_logged_in());
+    assert!(server.wait_until(Duration::from_secs(5), |server| server.filters().len() == 2));
+
+    server.send_line("LSTB>APRS,TCPIP*,qAC,GLIDERN2:/060854h4656.38NI00723.46E&/A=001877");
+    assert!(client.read().unwrap().message.starts_with("LSTB>APRS"));
+  }
+
+  #[test]
+  fn lazy_client_test() {
+    // a port without listener, connecting fails without name resolution
+    let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = PORT::Custom(closed.local_addr().unwrap().port(), PortCapabilities::FILTER);
+    drop(closed);
+
+    let client = APRSClient::new_lazy("127.0.0.1", port, Box::new(|_| {}));
+    let mut client = client.lock().unwrap();
+
+    assert!(!client.is_connected());
+    assert_eq!(client.peer_address(), None);
+    assert!(client.connect().is_err());
+  }
+}
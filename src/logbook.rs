@@ -0,0 +1,460 @@
+//! Takeoff and landing detection for automatic flight logbooks
+
+use crate::geo::distance_km;
+use crate::parser::{OgnBody, OgnTransmission};
+use crate::units::{Feet, FeetPerMinute, Knots};
+use chrono::{DateTime, Utc};
+use geocoding::Coordinate;
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FlightEventKind {
+    Takeoff,
+    Landing,
+}
+
+/// Takeoff or landing of an aircraft, time and location are those of the first beacon showing the
+/// new state
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FlightEvent {
+    pub kind: FlightEventKind,
+    pub sender_id: String,
+    pub time: DateTime<Utc>,
+    /// x: latitude, y: longitude [degrees]
+    #[cfg_attr(feature = "serde", serde(with = "crate::parser::position_serde"))]
+    pub position: Coordinate<f64>,
+    pub altitude: Option<Feet>,
+    /// The state changed during a coverage gap, the event happened before `time`
+    pub after_gap: bool,
+    /// Set on the takeoff of the second aircraft of a pair launching together, e.g. the glider
+    /// and the tow plane of an aerotow
+    pub launched_with: Option<String>,
+}
+
+/// First beacon showing a possible state change
+#[derive(Debug, Clone)]
+struct Candidate {
+    time: DateTime<Utc>,
+    position: Coordinate<f64>,
+    altitude: Option<Feet>,
+    after_gap: bool,
+    beacons: u32,
+}
+
+#[derive(Debug, Clone)]
+struct AircraftFlightState {
+    /// `None` until the beacons tell whether the aircraft is airborne
+    airborne: Option<bool>,
+    last_time: DateTime<Utc>,
+    /// Lowest altitude seen while the state is unknown
+    lowest_altitude: Option<Feet>,
+    /// Altitude of the last beacon on the ground
+    ground_altitude: Option<Feet>,
+    after_gap: bool,
+    candidate: Option<Candidate>,
+}
+
+impl AircraftFlightState {
+    fn new(time: DateTime<Utc>) -> Self {
+        Self {
+            airborne: None,
+            last_time: time,
+            lowest_altitude: None,
+            ground_altitude: None,
+            after_gap: false,
+            candidate: None,
+        }
+    }
+
+    /// Takes the state of an aircraft without event: airborne if climbing or sinking, if its
+    /// altitude rose by more than the margin or if flying faster than the takeoff speed, on the
+    /// ground if slower. Without speed the state stays unknown.
+    fn classify(&mut self, body: &OgnBody, detector: &FlightEventDetector) {
+        let climbing = matches!(body.climb_rate, Some(climb_rate) if climb_rate.0.abs() >= detector.climb_rate.0);
+        let risen = match (body.altitude, self.lowest_altitude) {
+            (Some(altitude), Some(lowest)) => altitude.0 > lowest.0 + detector.altitude_margin.0,
+            _ => false,
+        };
+
+        self.airborne = match body.ground_speed {
+            _ if climbing || risen => Some(true),
+            Some(speed) => Some(speed >= detector.takeoff_speed),
+            None => None,
+        };
+
+        if let Some(altitude) = body.altitude {
+            let lowest = match self.lowest_altitude {
+                Some(lowest) => altitude < lowest,
+                None => true,
+            };
+            if lowest {
+                self.lowest_altitude = Some(altitude);
+            }
+        }
+        if self.airborne == Some(false) {
+            self.ground_altitude = body.altitude;
+        }
+    }
+}
+
+/// Detects takeoffs and landings from the ground speed, altitude and climb rate of the beacons.
+/// A state change is only reported after `confirmations` consecutive beacons showing it, so single
+/// noisy beacons are ignored. An aircraft is airborne above `takeoff_speed` or `altitude_margin`
+/// above the altitude it had on the ground and on the ground again below `landing_speed` without
+/// climbing or sinking faster than `climb_rate`. Beacons without ground speed only count by their
+/// altitude. The state of an aircraft first seen is taken as is without an event, aircraft not
+/// seen within `timeout` are removed by `expire`.
+#[derive(Debug)]
+pub struct FlightEventDetector {
+    pub takeoff_speed: Knots,
+    pub landing_speed: Knots,
+    pub altitude_margin: Feet,
+    pub climb_rate: FeetPerMinute,
+    pub confirmations: u32,
+    /// Beacons further apart are considered a coverage gap
+    pub max_gap: Duration,
+    /// Takeoffs within this time and distance [km] are considered launching together
+    pub launch_window: Duration,
+    pub launch_distance_km: f64,
+    pub timeout: Duration,
+    aircraft: HashMap<String, AircraftFlightState>,
+    recent_takeoffs: VecDeque<FlightEvent>,
+}
+
+impl FlightEventDetector {
+    pub fn new() -> Self {
+        Self {
+            takeoff_speed: Knots(30.0),
+            landing_speed: Knots(15.0),
+            altitude_margin: Feet(300),
+            climb_rate: FeetPerMinute(200.0),
+            confirmations: 2,
+            max_gap: Duration::from_secs(600),
+            launch_window: Duration::from_secs(60),
+            launch_distance_km: 0.5,
+            timeout: Duration::from_secs(3600),
+            aircraft: HashMap::new(),
+            recent_takeoffs: VecDeque::new(),
+        }
+    }
+
+    /// Processes the beacon, returns the takeoff or landing it confirms. Beacons without OGN id
+    /// and beacons older than the last one of the aircraft are ignored.
+    pub fn update(&mut self, transmission: &OgnTransmission) -> Option<FlightEvent> {
+        let body = &transmission.body;
+        body.id.as_ref()?;
+
+        let sender_id = &transmission.header.sender_id;
+        if !self.aircraft.contains_key(sender_id) {
+            let mut state = AircraftFlightState::new(body.timestamp);
+            state.classify(body, self);
+            self.aircraft.insert(sender_id.clone(), state);
+            return None;
+        }
+
+        // taken out of the map while the detector decides on the beacon
+        let (sender_id, mut state) = self.aircraft.remove_entry(sender_id).unwrap();
+        let event = self.update_state(&mut state, transmission);
+        self.aircraft.insert(sender_id, state);
+
+        event
+    }
+
+    /// Whether the aircraft is airborne, `None` if it has not been seen or its state is not known
+    /// yet
+    pub fn is_airborne(&self, sender_id: &str) -> Option<bool> {
+        self.aircraft
+            .get(sender_id)
+            .and_then(|state| state.airborne)
+    }
+
+    pub fn expire(&mut self) -> Vec<String> {
+        self.expire_at(Utc::now())
+    }
+
+    /// Removes the aircraft without beacon within `timeout` before the given time, returns their
+    /// sender ids
+    pub fn expire_at(&mut self, now: DateTime<Utc>) -> Vec<String> {
+        let timeout =
+            chrono::Duration::from_std(self.timeout).unwrap_or(chrono::Duration::max_value());
+        let expired: Vec<String> = self
+            .aircraft
+            .iter()
+            .filter(|(_, state)| now.signed_duration_since(state.last_time) > timeout)
+            .map(|(sender_id, _)| sender_id.clone())
+            .collect();
+
+        for sender_id in expired.iter() {
+            self.aircraft.remove(sender_id);
+        }
+
+        let window =
+            chrono::Duration::from_std(self.launch_window).unwrap_or(chrono::Duration::max_value());
+        self.recent_takeoffs
+            .retain(|takeoff| now.signed_duration_since(takeoff.time) <= window);
+
+        expired
+    }
+
+    fn update_state(
+        &mut self,
+        state: &mut AircraftFlightState,
+        transmission: &OgnTransmission,
+    ) -> Option<FlightEvent> {
+        let body = &transmission.body;
+        if body.timestamp <= state.last_time {
+            return None;
+        }
+        let elapsed = (body.timestamp - state.last_time)
+            .to_std()
+            .unwrap_or_default();
+        state.last_time = body.timestamp;
+        if elapsed > self.max_gap {
+            state.after_gap = true;
+            state.candidate = None;
+        }
+
+        let airborne = match state.airborne {
+            Some(airborne) => airborne,
+            None => {
+                state.classify(body, self);
+                return None;
+            }
+        };
+
+        let changing = if airborne {
+            let slow = matches!(body.ground_speed, Some(speed) if speed < self.landing_speed);
+            let level = match body.climb_rate {
+                Some(climb_rate) => climb_rate.0.abs() < self.climb_rate.0,
+                None => true,
+            };
+            slow && level
+        } else {
+            let fast = matches!(body.ground_speed, Some(speed) if speed >= self.takeoff_speed);
+            let above_ground = match (body.altitude, state.ground_altitude) {
+                (Some(altitude), Some(ground)) => altitude.0 > ground.0 + self.altitude_margin.0,
+                _ => false,
+            };
+            fast || above_ground
+        };
+
+        if !changing {
+            state.candidate = None;
+            state.after_gap = false;
+            if !airborne && body.altitude.is_some() {
+                state.ground_altitude = body.altitude;
+            }
+            return None;
+        }
+
+        let after_gap = state.after_gap;
+        let candidate = state.candidate.get_or_insert(Candidate {
+            time: body.timestamp,
+            position: body.position,
+            altitude: body.altitude,
+            after_gap,
+            beacons: 0,
+        });
+        candidate.beacons += 1;
+        if candidate.beacons < self.confirmations.max(1) {
+            return None;
+        }
+
+        let candidate = state.candidate.take().unwrap();
+        state.airborne = Some(!airborne);
+        state.after_gap = false;
+        if airborne {
+            state.ground_altitude = body.altitude;
+        }
+
+        let mut event = FlightEvent {
+            kind: if airborne {
+                FlightEventKind::Landing
+            } else {
+                FlightEventKind::Takeoff
+            },
+            sender_id: transmission.header.sender_id.clone(),
+            time: candidate.time,
+            position: candidate.position,
+            altitude: candidate.altitude,
+            after_gap: candidate.after_gap,
+            launched_with: None,
+        };
+
+        if event.kind == FlightEventKind::Takeoff {
+            event.launched_with = self.launch_partner(&event);
+            self.recent_takeoffs.push_back(event.clone());
+        }
+
+        Some(event)
+    }
+
+    fn launch_partner(&mut self, takeoff: &FlightEvent) -> Option<String> {
+        let window = chrono::Duration::from_std(self.launch_window).ok()?;
+        while let Some(oldest) = self.recent_takeoffs.front() {
+            if takeoff.time - oldest.time <= window {
+                break;
+            }
+            self.recent_takeoffs.pop_front();
+        }
+
+        self.recent_takeoffs
+            .iter()
+            .filter(|other| other.sender_id != takeoff.sender_id)
+            .filter(|other| (takeoff.time - other.time).num_seconds().abs() <= window.num_seconds())
+            .find(|other| distance_km(other.position, takeoff.position) <= self.launch_distance_km)
+            .map(|other| other.sender_id.clone())
+    }
+}
+
+impl Default for FlightEventDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parse;
+    use chrono::TimeZone;
+
+    fn beacon(sender: &str, time: &str, speed: u16, altitude: u32) -> OgnTransmission {
+        OgnTransmission::parse_at(
+            &format!(
+                "{}>APRS,qAS,LSZG:/{}h4711.50N/00731.00E'270/{:03}/A={:06} !W00! id06DDA5BA +000fpm",
+                sender, time, speed, altitude
+            ),
+            Utc.ymd(2021, 6, 1).and_hms(12, 0, 0),
+        )
+        .unwrap()
+    }
+
+    fn events(
+        detector: &mut FlightEventDetector,
+        beacons: &[(&str, &str, u16, u32)],
+    ) -> Vec<FlightEvent> {
+        beacons
+            .iter()
+            .filter_map(|(sender, time, speed, altitude)| {
+                detector.update(&beacon(sender, time, *speed, *altitude))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn takeoff_and_landing_test() {
+        let mut detector = FlightEventDetector::new();
+
+        let detected = events(
+            &mut detector,
+            &[
+                ("FLRDDA5BA", "100000", 0, 1411),
+                // a single noisy beacon
+                ("FLRDDA5BA", "100010", 45, 1411),
+                ("FLRDDA5BA", "100020", 0, 1411),
+                ("FLRDDA5BA", "100030", 40, 1411),
+                ("FLRDDA5BA", "100040", 55, 1500),
+                ("FLRDDA5BA", "100500", 50, 4000),
+                ("FLRDDA5BA", "101400", 10, 1411),
+                ("FLRDDA5BA", "101410", 0, 1411),
+            ],
+        );
+
+        assert_eq!(detected.len(), 2);
+        assert_eq!(detected[0].kind, FlightEventKind::Takeoff);
+        assert_eq!(detected[0].time, Utc.ymd(2021, 6, 1).and_hms(10, 0, 30));
+        assert_eq!(detected[0].altitude, Some(Feet(1411)));
+        assert_eq!(detected[1].kind, FlightEventKind::Landing);
+        assert_eq!(detected[1].time, Utc.ymd(2021, 6, 1).and_hms(10, 14, 0));
+        assert!(!detected[1].after_gap);
+        assert_eq!(detector.is_airborne("FLRDDA5BA"), Some(false));
+        assert_eq!(detector.is_airborne("FLRDDB1C2"), None);
+    }
+
+    #[test]
+    fn coverage_gap_test() {
+        let mut detector = FlightEventDetector::new();
+
+        // first seen airborne, then out of coverage until after the landing
+        let detected = events(
+            &mut detector,
+            &[
+                ("FLRDDA5BA", "100000", 50, 4000),
+                ("FLRDDA5BA", "100010", 50, 4000),
+                ("FLRDDA5BA", "104000", 0, 1411),
+                ("FLRDDA5BA", "104010", 0, 1411),
+            ],
+        );
+
+        assert_eq!(detected.len(), 1);
+        assert_eq!(detected[0].kind, FlightEventKind::Landing);
+        assert!(detected[0].after_gap);
+    }
+
+    #[test]
+    fn aerotow_test() {
+        let mut detector = FlightEventDetector::new();
+
+        let detected = events(
+            &mut detector,
+            &[
+                ("FLRDDA5BA", "100000", 0, 1411),
+                ("FLRDDB1C2", "100001", 0, 1411),
+                ("FLRDDA5BA", "100100", 35, 1411),
+                ("FLRDDB1C2", "100102", 35, 1411),
+                ("FLRDDA5BA", "100110", 55, 1450),
+                ("FLRDDB1C2", "100112", 55, 1450),
+            ],
+        );
+
+        assert_eq!(detected.len(), 2);
+        assert_eq!(detected[0].sender_id, "FLRDDA5BA");
+        assert_eq!(detected[0].launched_with, None);
+        assert_eq!(detected[1].sender_id, "FLRDDB1C2");
+        assert_eq!(detected[1].launched_with.as_deref(), Some("FLRDDA5BA"));
+    }
+
+    #[test]
+    fn unknown_speed_and_expire_test() {
+        let mut detector = FlightEventDetector::new();
+        let without_speed = |time: &str, altitude: u32| {
+            OgnTransmission::parse_at(
+                &format!(
+                    "FLRDDA5BA>APRS,qAS,LSZG:/{}h4711.50N/00731.00E'/A={:06} !W00! id06DDA5BA +000fpm",
+                    time, altitude
+                ),
+                Utc.ymd(2021, 6, 1).and_hms(12, 0, 0),
+            )
+            .unwrap()
+        };
+
+        // without speed the state is not known until the altitude changes
+        assert_eq!(detector.update(&without_speed("100000", 4000)), None);
+        assert_eq!(detector.is_airborne("FLRDDA5BA"), None);
+        assert_eq!(detector.update(&without_speed("100010", 4400)), None);
+        assert_eq!(detector.is_airborne("FLRDDA5BA"), Some(true));
+
+        let detected = events(
+            &mut detector,
+            &[
+                ("FLRDDA5BA", "101000", 0, 1411),
+                ("FLRDDA5BA", "101010", 0, 1411),
+            ],
+        );
+        assert_eq!(detected.len(), 1);
+        assert_eq!(detected[0].kind, FlightEventKind::Landing);
+
+        assert!(detector
+            .expire_at(Utc.ymd(2021, 6, 1).and_hms(10, 30, 0))
+            .is_empty());
+        assert_eq!(
+            detector.expire_at(Utc.ymd(2021, 6, 1).and_hms(12, 0, 0)),
+            vec!["FLRDDA5BA".to_string()]
+        );
+        assert_eq!(detector.is_airborne("FLRDDA5BA"), None);
+    }
+}
@@ -1,9 +1,8 @@
-mod parser;
 use log::debug;
-use parser::Parse;
 use std::io::Error;
 
-use ogn_client_rs::{APRSClient, LoginData, PORT};
+use ogn_client_rs::parser::{self, Parse};
+use ogn_client_rs::{APRSClient, LoginData, ReceivedMessage, PORT};
 
 fn main() -> Result<(), Error> {
     log4rs::init_file("logger_config.yaml", Default::default()).unwrap();
@@ -11,9 +10,9 @@ fn main() -> Result<(), Error> {
     // ------------------------------------------------------------------------------
     // Here comes the interesting part
     // ------------------------------------------------------------------------------
-    let callback = |message: &str| {
+    let callback = |message: &ReceivedMessage| {
         // This does not work completely yet...
-        let result = parser::OgnTransmission::parse(message);
+        let result = parser::AprsPacket::parse_at(&message.message, message.received_utc);
         if let Some(v) = result {
             debug!("read message");
             debug!("{:#?}", v);
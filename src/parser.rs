@@ -1,67 +1,242 @@
 // TODO add parser error type or use serde?
 
+use crate::timestamp::AprsTimestamp;
+use crate::units::{Feet, FeetPerMinute, HalfTurnsPerMinute, Knots};
+use crate::OgnStatusMessage;
 use chrono::prelude::*;
 use geocoding::Coordinate;
 use log::{debug, error};
 use regex::Regex;
 use std::fmt::Debug;
+use std::net::SocketAddr;
 
 pub trait Parse {
     type Item: Debug;
 
     fn parse(string: &str) -> Option<Self::Item>;
+
+    /// Parses the string and resolves timestamps against the given reference time, e.g. the
+    /// reception time or the time of a log entry
+    fn parse_at(string: &str, _reference: DateTime<Utc>) -> Option<Self::Item> {
+        Self::parse(string)
+    }
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OgnTransmission {
     pub header: OgnHeader,
     pub body: OgnBody,
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OgnHeader {
     pub sender_id: String,
+    pub target: String,
+    /// Stations the packet passed before the q construct, e.g. `TCPIP*` or `WIDE1-1`
+    pub path: Vec<String>,
     pub receiver: String,
     pub transmission_method: String,
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OgnBody {
     pub timestamp: DateTime<Utc>, // Utc
-    pub position: Coordinate<f32>,
-    pub ground_speed: f32, // [knots]
-    pub ground_turning_rate: Option<f32>,
-    pub climb_rate: Option<f32>, // [fpm]
-    pub altitude: f32,           // [feet]
-    pub ground_track: u16,       // [degrees]
+    #[cfg_attr(feature = "serde", serde(with = "position_serde"))]
+    pub position: Coordinate<f64>, // x: latitude, y: longitude [degrees]
+    /// The position carries the `!Wab!` precision enhancement (third decimal of the minutes)
+    pub precision_enhancement: bool,
+    pub symbol_table: char,
+    pub symbol_code: char,
+    pub ground_speed: Option<Knots>,
+    pub ground_turning_rate: Option<HalfTurnsPerMinute>,
+    pub climb_rate: Option<FeetPerMinute>,
+    pub altitude: Option<Feet>,
+    pub ground_track: Option<u16>, // [degrees]
     pub gps_accuracy: Option<String>,
+    /// Signal to noise ratio [dB] at the receiver
+    pub signal_strength: Option<f32>,
     pub id: Option<String>,
+    pub mic_e: Option<MicE>,
+}
+
+/// Positions are serialized as `{"latitude": .., "longitude": ..}` in decimal degrees
+#[cfg(feature = "serde")]
+pub(crate) mod position_serde {
+    use geocoding::Coordinate;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct Position {
+        latitude: f64,
+        longitude: f64,
+    }
+
+    pub fn serialize<S: Serializer>(
+        position: &Coordinate<f64>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        Position {
+            latitude: position.x,
+            longitude: position.y,
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Coordinate<f64>, D::Error> {
+        let position = Position::deserialize(deserializer)?;
+
+        Ok(Coordinate {
+            x: position.latitude,
+            y: position.longitude,
+        })
+    }
+}
+
+/// Additional information carried by Mic-E encoded position reports
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MicE {
+    /// The three message bits A, B and C encoded in the destination field (A is the most significant bit)
+    pub message_bits: u8,
+    pub message: MicEMessage,
+    pub manufacturer: Option<String>,
+    pub device: Option<String>,
+}
+
+/// Mic-E message types, see chapter 10 of the APRS 1.01 specification
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MicEMessage {
+    OffDuty,
+    EnRoute,
+    InService,
+    Returning,
+    Committed,
+    Special,
+    Priority,
+    /// Custom message C0 - C6
+    Custom(u8),
+    Emergency,
+    /// Standard and custom message bits are mixed
+    Unknown,
+}
+
+/// An APRS message addressed to a station, see chapter 14 of the APRS 1.01 specification
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AprsMessage {
+    pub addressee: String,
+    pub content: AprsMessageContent,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AprsMessageContent {
+    /// Message text with the optional message id which has to be acknowledged
+    Text { text: String, id: Option<String> },
+    /// Acknowledgement of the message with the given id
+    Ack(String),
+    /// Rejection of the message with the given id
+    Rej(String),
+}
+
+impl AprsMessage {
+    /// Returns true if the message is addressed to the given callsign (including the ssid)
+    pub fn is_addressed_to(&self, callsign: &str) -> bool {
+        self.addressee.eq_ignore_ascii_case(callsign.trim())
+    }
+}
+
+/// An APRS object, e.g. a winch position or an airfield marker
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AprsObject {
+    pub name: String,
+    /// false if the object has been killed
+    pub alive: bool,
+    pub timestamp: Option<DateTime<Utc>>,
+    #[cfg_attr(feature = "serde", serde(with = "position_serde"))]
+    pub position: Coordinate<f64>, // x: latitude, y: longitude [degrees]
+    pub symbol_table: char,
+    pub symbol_code: char,
+    pub comment: String,
+}
+
+/// An APRS item, like an object but without timestamp
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AprsItem {
+    pub name: String,
+    /// false if the item has been killed
+    pub alive: bool,
+    #[cfg_attr(feature = "serde", serde(with = "position_serde"))]
+    pub position: Coordinate<f64>, // x: latitude, y: longitude [degrees]
+    pub symbol_table: char,
+    pub symbol_code: char,
+    pub comment: String,
+}
+
+/// Comment line sent by the server, starting with `#`: the banner after connecting, e.g.
+/// `# aprsc 2.1.10-gd72a17c`, and the periodic keepalive, e.g.
+/// `# aprsc 2.1.10-gd72a17c 18 Oct 2026 12:00:00 GMT GLIDERN3 1.2.3.4:14580`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ServerComment {
+    /// The comment without the leading `#`
+    pub text: String,
+    pub software: Option<String>,
+    pub version: Option<String>,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub server_name: Option<String>,
+    pub address: Option<SocketAddr>,
+}
+
+impl ServerComment {
+    /// Difference between the local time and the server time, positive if the local clock is
+    /// ahead. The server time has a resolution of one second.
+    pub fn clock_skew(&self, local: DateTime<Utc>) -> Option<chrono::Duration> {
+        Some(local - self.timestamp?)
+    }
+}
+
+/// Any packet of the feed distinguished by the data type identifier of its body
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AprsPacket {
+    Position(OgnTransmission),
+    Message(OgnHeader, AprsMessage),
+    Object(OgnHeader, AprsObject),
+    Item(OgnHeader, AprsItem),
+    Status(OgnHeader, OgnStatusMessage),
+    ServerComment(ServerComment),
 }
 
 impl Parse for OgnTransmission {
     type Item = Self;
 
     fn parse(message: &str) -> Option<Self> {
-        if message.starts_with("#") {
-            debug!("received comment: {}", message);
-            return None;
-        }
-
-        // TODO add more checks before actually parsing, e.g. station info, heartbeats etc.
+        Self::parse_at(message, Utc::now())
+    }
 
-        // first: split at ':' to split the header from the message
-        let splits: Vec<&str> = message.split(':').collect();
+    fn parse_at(message: &str, reference: DateTime<Utc>) -> Option<Self> {
+        let (header, body) = split_message(message)?;
 
-        let header = parse_header(splits[0]);
-        if let None = header {
-            error!("error while parsing header, skipping message: {}", message);
+        // status lines (e.g. of the receivers) are regular traffic, not a parse error
+        if body.starts_with('>') {
+            debug!(
+                "status line is not a position, skipping message: {}",
+                message
+            );
             return None;
         }
 
-        let header = header.unwrap();
-
-        let body = parse_body(splits[1]);
-        if let None = body {
+        let body = parse_position(&header, body, reference);
+        if body.is_none() {
             error!("error while parsing body, skipping message: {}", message);
             return None;
         }
@@ -72,201 +247,720 @@ impl Parse for OgnTransmission {
     }
 }
 
+impl Parse for AprsPacket {
+    type Item = Self;
+
+    fn parse(message: &str) -> Option<Self> {
+        Self::parse_at(message, Utc::now())
+    }
+
+    fn parse_at(message: &str, reference: DateTime<Utc>) -> Option<Self> {
+        if message.starts_with('#') {
+            return ServerComment::parse(message).map(AprsPacket::ServerComment);
+        }
+
+        let (header, body) = split_message(message)?;
+
+        let packet = match body.chars().next() {
+            Some(':') => parse_message(body).map(|v| AprsPacket::Message(header, v)),
+            Some(';') => parse_object(body, reference).map(|v| AprsPacket::Object(header, v)),
+            Some(')') => parse_item(body).map(|v| AprsPacket::Item(header, v)),
+            Some('>') => OgnStatusMessage::parse(body).map(|v| AprsPacket::Status(header, v)),
+            _ => parse_position(&header, body, reference)
+                .map(|body| AprsPacket::Position(OgnTransmission { header, body })),
+        };
+
+        if packet.is_none() {
+            if body.starts_with('>') {
+                // receiver status lines carry no OGN status values
+                debug!("skipping status line: {}", message);
+            } else {
+                error!("error while parsing body, skipping message: {}", message);
+            }
+        }
+
+        packet
+    }
+}
+
+/// Splits the message into the parsed header and the raw body
+fn split_message(message: &str) -> Option<(OgnHeader, &str)> {
+    if message.starts_with('#') {
+        debug!("received comment: {}", message);
+        return None;
+    }
+
+    // TODO add more checks before actually parsing, e.g. station info, heartbeats etc.
+
+    // first: split at the first ':' to split the header from the message
+    let (header, body) = match message.split_once(':') {
+        Some(v) => v,
+        None => {
+            error!("no header separator found, skipping message: {}", message);
+            return None;
+        }
+    };
+
+    let header = parse_header(header);
+    if header.is_none() {
+        error!("error while parsing header, skipping message: {}", message);
+        return None;
+    }
+
+    Some((header.unwrap(), body))
+}
+
+fn parse_position(header: &OgnHeader, body: &str, reference: DateTime<Utc>) -> Option<OgnBody> {
+    if is_mic_e(body) {
+        parse_mic_e(&header.target, body, reference)
+    } else {
+        parse_body(body, reference)
+    }
+}
+
 fn parse_header(header: &str) -> Option<OgnHeader> {
     // parse header with regex
-    let regex = Regex::new(r"^(?P<id>\w+)>(?P<target>.+),(?P<protocol>\w+),(?P<receiver>\w+)$")
-        .expect("error in header regex");
+    let regex = Regex::new(
+        r"^(?P<id>[^>]+)>(?P<target>[^,]+)(?:,(?P<path>.+))?,(?P<protocol>\w+),(?P<receiver>\w+)$",
+    )
+    .expect("error in header regex");
 
     let captures = regex.captures(header)?;
 
     let sender_id = captures.name("id").unwrap().as_str();
-    let _target = captures.name("target").unwrap().as_str();
+    let target = captures.name("target").unwrap().as_str();
     let transmission_method = captures.name("protocol").unwrap().as_str();
     let receiver = captures.name("receiver").unwrap().as_str();
+    let path = match captures.name("path") {
+        Some(path) => path.as_str().split(',').map(str::to_string).collect(),
+        None => Vec::new(),
+    };
 
     Some(OgnHeader {
         sender_id: sender_id.to_string(),
+        target: target.to_string(),
+        path,
         receiver: receiver.to_string(),
         transmission_method: transmission_method.to_string(),
     })
 }
 
-fn parse_body(body: &str) -> Option<OgnBody> {
+fn parse_body(body: &str, reference: DateTime<Utc>) -> Option<OgnBody> {
     // Parse aprs fields, required for a regular transmission
-    let timestamp = parse_time(body);
-    if let None = timestamp {
+    let timestamp = parse_time(body, reference);
+    if timestamp.is_none() {
         error!("no timestamp in body: '{}' ignoring message", body);
         return None;
     }
     let timestamp = timestamp.unwrap();
 
     let coordinate = parse_coordinate(body);
-    if let None = coordinate {
+    if coordinate.is_none() {
         error!("no coordinate in body: '{}' ignoring message", body);
 
         return None;
     }
     let coordinate = coordinate.unwrap();
 
-    let ground_track = parse_ground_track(body);
-    if let None = ground_track {
-        error!("no ground_track in body: '{}' ignoring message", body);
+    let symbol = parse_symbol(body);
+    if symbol.is_none() {
+        error!("no symbol in body: '{}' ignoring message", body);
         return None;
     }
-    let ground_track = ground_track.unwrap();
+    let (symbol_table, symbol_code) = symbol.unwrap();
 
+    // course/speed and altitude are not sent by every station (e.g. receiver beacons)
+    let ground_track = parse_ground_track(body);
     let ground_speed = parse_ground_speed(body);
-    if let None = ground_speed {
-        error!("no ground_speed in body: '{}' ignoring message", body);
-        return None;
-    }
-    let ground_speed = ground_speed.unwrap();
-
     let altitude = parse_altitude(body);
-    if let None = altitude {
-        error!("no altitude in body: '{}' ignoring message", body);
-        return None;
-    }
-    let altitude = altitude.unwrap();
 
     // parse ogn part
     let id = parse_id(body);
     let climb_rate = parse_climb_rate(body);
     let rotation_rate = parse_rotation_rate(body);
     let gps_accuracy = parse_gps_accuracy(body);
+    let signal_strength = parse_signal_strength(body);
 
     // assemble the message
     Some(OgnBody {
-        timestamp: timestamp,
+        timestamp,
         position: coordinate,
-        ground_speed: ground_speed,
+        precision_enhancement: parse_precision_enhancement(body).is_some(),
+        symbol_table,
+        symbol_code,
+        ground_speed,
         ground_turning_rate: rotation_rate,
-        climb_rate: climb_rate,
-        altitude: altitude,
-        ground_track: ground_track,
-        gps_accuracy: gps_accuracy,
-        id: id,
+        climb_rate,
+        altitude,
+        ground_track,
+        gps_accuracy,
+        signal_strength,
+        id,
+        mic_e: None,
     })
 }
 
-#[allow(dead_code)]
 pub fn parse_login_answer(login_answer: &str) -> bool {
     let re = Regex::new(r"^# logresp (\w+) (verified)").unwrap();
 
-    match re.find(login_answer) {
-        Some(_) => true,
-        _ => false,
-    }
+    re.find(login_answer).is_some()
 }
 
-fn parse_time(body: &str) -> Option<DateTime<Utc>> {
-    let regex = Regex::new(r"(?P<time>\d{6})h").unwrap();
-    let captures = regex.captures(body);
-    if let Some(v) = captures {
-        let time_string = v.name("time").unwrap().as_str();
-        let h: u32 = time_string[..2].parse().unwrap();
-        let m: u32 = time_string[2..4].parse().unwrap();
-        let s: u32 = time_string[4..].parse().unwrap();
-        Some(Utc::today().and_hms(h, m, s))
-    } else {
-        None
-    }
+/// Extracts the server name from the login answer, e.g. `GLIDERN2` from
+/// `# logresp user verified, server GLIDERN2`
+pub fn parse_login_server(login_answer: &str) -> Option<String> {
+    let re = Regex::new(r"^# logresp .*, server (?P<server>\S+)").unwrap();
+    let captures = re.captures(login_answer)?;
+
+    Some(captures.name("server").unwrap().as_str().to_string())
 }
 
-fn parse_coordinate(body: &str) -> Option<Coordinate<f32>> {
-    let regex = Regex::new(r"(?P<lat>[0-9.]+)[NS].{2}(?P<lon>[0-9.]+)[EW]").unwrap();
-    let captures = regex.captures(body);
+fn parse_time(body: &str, reference: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    // the timestamp directly follows the data type identifier
+    let timestamp = body.strip_prefix(|c| c == '/' || c == '@').unwrap_or(body);
 
-    if let Some(v) = captures {
-        let lat = v.name("lat").unwrap().as_str().replace(".", "");
-        let lon = v.name("lon").unwrap().as_str().replace(".", "");
+    AprsTimestamp::parse(timestamp)?.resolve(reference)
+}
 
-        Some(Coordinate {
-            x: &lat[0..2].parse::<f32>().unwrap()
-                + &lat[2..4].parse::<f32>().unwrap() / 60.0
-                + &lat[4..6].parse::<f32>().unwrap() / 3600.0,
+/// Converts degrees and thousandths of minutes into decimal degrees
+pub(crate) fn to_decimal_degrees(degrees: u32, minute_thousandths: u32) -> f64 {
+    degrees as f64 + (minute_thousandths as f64 / 1000.0) / 60.0
+}
 
-            y: &lon[0..2].parse::<f32>().unwrap()
-                + &lon[2..4].parse::<f32>().unwrap() / 60.0
-                + &lon[4..6].parse::<f32>().unwrap() / 3600.0,
-        })
-    } else {
-        None
+fn parse_coordinate(body: &str) -> Option<Coordinate<f64>> {
+    let regex = Regex::new(
+        r"(?P<lat_deg>\d{2})(?P<lat_min>\d{2}\.\d{2})(?P<ns>[NS]).(?P<lon_deg>\d{3})(?P<lon_min>\d{2}\.\d{2})(?P<ew>[EW])",
+    )
+    .unwrap();
+    let captures = regex.captures(body)?;
+
+    // the precision enhancement '!Wab!' adds a third decimal digit to the minutes
+    let (lat_enhancement, lon_enhancement) = parse_precision_enhancement(body).unwrap_or((0, 0));
+
+    let minute_thousandths = |name: &str, enhancement: u32| -> u32 {
+        let hundredths: u32 = captures
+            .name(name)
+            .unwrap()
+            .as_str()
+            .replace('.', "")
+            .parse()
+            .unwrap();
+        hundredths * 10 + enhancement
+    };
+
+    let mut latitude = to_decimal_degrees(
+        captures.name("lat_deg").unwrap().as_str().parse().unwrap(),
+        minute_thousandths("lat_min", lat_enhancement),
+    );
+    let mut longitude = to_decimal_degrees(
+        captures.name("lon_deg").unwrap().as_str().parse().unwrap(),
+        minute_thousandths("lon_min", lon_enhancement),
+    );
+
+    if captures.name("ns").unwrap().as_str() == "S" {
+        latitude = -latitude;
+    }
+    if captures.name("ew").unwrap().as_str() == "W" {
+        longitude = -longitude;
     }
+
+    Some(Coordinate {
+        x: latitude,
+        y: longitude,
+    })
+}
+
+fn parse_precision_enhancement(body: &str) -> Option<(u32, u32)> {
+    let regex = Regex::new(r"!W(?P<lat>\d)(?P<lon>\d)!").unwrap();
+    let captures = regex.captures(body)?;
+
+    Some((
+        captures.name("lat").unwrap().as_str().parse().unwrap(),
+        captures.name("lon").unwrap().as_str().parse().unwrap(),
+    ))
+}
+
+fn parse_symbol(body: &str) -> Option<(char, char)> {
+    let regex = Regex::new(r"\d{4}\.\d{2}[NS](?P<table>.)\d{5}\.\d{2}[EW](?P<code>.)").unwrap();
+    let captures = regex.captures(body)?;
+
+    Some((
+        captures.name("table").unwrap().as_str().chars().next()?,
+        captures.name("code").unwrap().as_str().chars().next()?,
+    ))
 }
 
 fn parse_ground_track(body: &str) -> Option<u16> {
-    let regex = Regex::new(r"\d{6}h.*[WE].(?P<ground_track>\d{3})/.*A=").unwrap();
+    // course and speed directly follow the symbol code
+    let regex = Regex::new(r"\d{5}\.\d{2}[EW].(?P<ground_track>\d{3})/\d{3}").unwrap();
     let captures = regex.captures(body);
 
-    if let Some(v) = captures {
-        Some(v.name("ground_track").unwrap().as_str().parse().unwrap())
-    } else {
-        None
-    }
+    captures.map(|v| v.name("ground_track").unwrap().as_str().parse().unwrap())
 }
 
-fn parse_ground_speed(body: &str) -> Option<f32> {
-    let regex = Regex::new(r"/\d{6}.*[NS].*[EW].\d{3}/(?P<ground_speed>\d{3})").unwrap();
+fn parse_ground_speed(body: &str) -> Option<Knots> {
+    let regex = Regex::new(r"\d{5}\.\d{2}[EW].\d{3}/(?P<ground_speed>\d{3})").unwrap();
     let captures = regex.captures(body);
 
-    if let Some(v) = captures {
-        Some(v.name("ground_speed").unwrap().as_str().parse().unwrap())
-    } else {
-        None
-    }
+    captures.map(|v| Knots(v.name("ground_speed").unwrap().as_str().parse().unwrap()))
 }
 
-fn parse_altitude(body: &str) -> Option<f32> {
-    let regex = Regex::new(r"A=(?P<altitude>\d{6})").unwrap();
+fn parse_altitude(body: &str) -> Option<Feet> {
+    let regex = Regex::new(r"A=(?P<altitude>-?\d{5,6})").unwrap();
     let captures = regex.captures(body);
 
-    if let Some(v) = captures {
-        Some(v.name("altitude").unwrap().as_str().parse().unwrap())
-    } else {
-        None
-    }
+    captures.map(|v| Feet(v.name("altitude").unwrap().as_str().parse().unwrap()))
 }
 
 fn parse_id(body: &str) -> Option<String> {
-    let regex = Regex::new(r"id(?P<id>\w{8})").unwrap();
+    let regex = Regex::new(r"\bid(?P<id>[0-9A-F]{8})\b").unwrap();
     let captures = regex.captures(body);
 
-    if let Some(v) = captures {
-        Some(v.name("id").unwrap().as_str().parse().unwrap())
-    } else {
-        None
-    }
+    captures.map(|v| v.name("id").unwrap().as_str().to_string())
 }
 
-fn parse_climb_rate(body: &str) -> Option<f32> {
-    let regex = Regex::new(r"(?P<climb>[+-].*)fpm").unwrap();
+fn parse_climb_rate(body: &str) -> Option<FeetPerMinute> {
+    let regex = Regex::new(r"(?P<climb>[+-]\d+)fpm").unwrap();
     let captures = regex.captures(body);
-    if let Some(v) = captures {
-        Some(v.name("climb").unwrap().as_str().parse().unwrap())
-    } else {
-        None
-    }
+
+    captures.map(|v| FeetPerMinute(v.name("climb").unwrap().as_str().parse().unwrap()))
 }
 
-fn parse_rotation_rate(body: &str) -> Option<f32> {
-    let regex = Regex::new(r"(?P<rot>[+-][0-9.]+)rot").unwrap();
+fn parse_rotation_rate(body: &str) -> Option<HalfTurnsPerMinute> {
+    let regex = Regex::new(r"(?P<rot>[+-][0-9]+(\.[0-9]+)?)rot").unwrap();
     let captures = regex.captures(body);
-    if let Some(v) = captures {
-        Some(v.name("rot").unwrap().as_str().parse().unwrap())
-    } else {
-        None
-    }
+
+    captures.and_then(|v| {
+        v.name("rot")
+            .unwrap()
+            .as_str()
+            .parse()
+            .ok()
+            .map(HalfTurnsPerMinute)
+    })
+}
+
+fn parse_signal_strength(body: &str) -> Option<f32> {
+    let regex = Regex::new(r" (?P<snr>[+-]?[0-9]+\.[0-9]+)dB\b").unwrap();
+    let captures = regex.captures(body);
+
+    captures.and_then(|v| v.name("snr").unwrap().as_str().parse().ok())
 }
 
 fn parse_gps_accuracy(body: &str) -> Option<String> {
-    let regex = Regex::new(r"gps(?P<accuracy>\dx\d)").unwrap();
+    let regex = Regex::new(r"gps(?P<accuracy>\d+x\d+)").unwrap();
     let captures = regex.captures(body);
-    if let Some(v) = captures {
-        Some(v.name("accuracy").unwrap().as_str().parse().unwrap())
+
+    captures.map(|v| v.name("accuracy").unwrap().as_str().to_string())
+}
+
+// ------------------------------------------------------------------------------
+// Messages, objects and items
+// ------------------------------------------------------------------------------
+
+fn parse_message(body: &str) -> Option<AprsMessage> {
+    let regex = Regex::new(r"^:(?P<addressee>.{9}):(?P<text>.*)$").unwrap();
+    let captures = regex.captures(body)?;
+
+    let addressee = captures.name("addressee").unwrap().as_str().trim_end();
+    let text = captures.name("text").unwrap().as_str().trim_end();
+
+    let ack_regex = Regex::new(r"^(?P<kind>ack|rej)(?P<id>[[:alnum:]]{1,5})$").unwrap();
+    let content = if let Some(v) = ack_regex.captures(text) {
+        let id = v.name("id").unwrap().as_str().to_string();
+        match v.name("kind").unwrap().as_str() {
+            "ack" => AprsMessageContent::Ack(id),
+            _ => AprsMessageContent::Rej(id),
+        }
     } else {
-        None
+        // the message id follows a '{', a reply-ack may be appended after a '}'
+        let id_regex = Regex::new(r"\{(?P<id>[[:alnum:]]{1,5})(\}[[:alnum:]]{0,5})?$").unwrap();
+        match id_regex.captures(text) {
+            Some(v) => AprsMessageContent::Text {
+                text: text[..v.get(0).unwrap().start()].to_string(),
+                id: Some(v.name("id").unwrap().as_str().to_string()),
+            },
+            None => AprsMessageContent::Text {
+                text: text.to_string(),
+                id: None,
+            },
+        }
+    };
+
+    Some(AprsMessage {
+        addressee: addressee.to_string(),
+        content,
+    })
+}
+
+fn parse_object(body: &str, reference: DateTime<Utc>) -> Option<AprsObject> {
+    let regex = Regex::new(
+        r"^;(?P<name>.{9})(?P<state>[*_])(?P<time>\d{6}[hz/])(?P<position>\d{4}\.\d{2}[NS](?P<table>.)\d{5}\.\d{2}[EW](?P<symbol>.))(?P<comment>.*)$",
+    )
+    .unwrap();
+    let captures = regex.captures(body)?;
+
+    let comment = captures.name("comment").unwrap().as_str();
+    let position = format!(
+        "{} {}",
+        captures.name("position").unwrap().as_str(),
+        comment
+    );
+
+    Some(AprsObject {
+        name: captures
+            .name("name")
+            .unwrap()
+            .as_str()
+            .trim_end()
+            .to_string(),
+        alive: captures.name("state").unwrap().as_str() == "*",
+        timestamp: parse_time(captures.name("time").unwrap().as_str(), reference),
+        position: parse_coordinate(&position)?,
+        symbol_table: captures.name("table").unwrap().as_str().chars().next()?,
+        symbol_code: captures.name("symbol").unwrap().as_str().chars().next()?,
+        comment: comment.trim().to_string(),
+    })
+}
+
+fn parse_item(body: &str) -> Option<AprsItem> {
+    let regex = Regex::new(
+        r"^\)(?P<name>[^!_]{3,9})(?P<state>[!_])(?P<position>\d{4}\.\d{2}[NS](?P<table>.)\d{5}\.\d{2}[EW](?P<symbol>.))(?P<comment>.*)$",
+    )
+    .unwrap();
+    let captures = regex.captures(body)?;
+
+    let comment = captures.name("comment").unwrap().as_str();
+    let position = format!(
+        "{} {}",
+        captures.name("position").unwrap().as_str(),
+        comment
+    );
+
+    Some(AprsItem {
+        name: captures.name("name").unwrap().as_str().to_string(),
+        alive: captures.name("state").unwrap().as_str() == "!",
+        position: parse_coordinate(&position)?,
+        symbol_table: captures.name("table").unwrap().as_str().chars().next()?,
+        symbol_code: captures.name("symbol").unwrap().as_str().chars().next()?,
+        comment: comment.trim().to_string(),
+    })
+}
+
+impl Parse for OgnStatusMessage {
+    type Item = Self;
+
+    /// Parses the `Key=Value` pairs of an OGN tracker status body, e.g.
+    /// `>130208h Pilot=John_Smith Manuf=Schempp-Hirth Model=Discus`. The leading `>` and the
+    /// timestamp are optional, unknown keys are skipped. `_` is read as space, see
+    /// `OgnStatusMessage::validate`. Status lines without any known key (e.g.
+    /// receiver status) are not considered an OGN status message.
+    fn parse(body: &str) -> Option<Self> {
+        let mut status = OgnStatusMessage::default();
+        let mut found = false;
+
+        for token in body.trim_start_matches('>').split_whitespace() {
+            if let Some((key, value)) = token.split_once('=') {
+                if let Some(field) = status.field_mut(key) {
+                    *field = Some(value.replace('_', " "));
+                    found = true;
+                }
+            }
+        }
+
+        if found {
+            Some(status)
+        } else {
+            None
+        }
+    }
+}
+
+impl Parse for ServerComment {
+    type Item = Self;
+
+    /// Parses any line starting with `#`, the software, version, time, server name and address
+    /// are only set for banner and keepalive lines
+    fn parse(line: &str) -> Option<Self> {
+        let text = line.strip_prefix('#')?.trim();
+        let mut comment = ServerComment {
+            text: text.to_string(),
+            software: None,
+            version: None,
+            timestamp: None,
+            server_name: None,
+            address: None,
+        };
+
+        let re = Regex::new(
+            r"^(?P<software>[A-Za-z]\S*) (?P<version>\d\S*)(?: (?P<time>\d{1,2} \w{3} \d{4} \d{2}:\d{2}:\d{2}) GMT (?P<server>\S+) (?P<address>\S+))?$",
+        )
+        .unwrap();
+
+        if let Some(captures) = re.captures(text) {
+            comment.software = Some(captures.name("software").unwrap().as_str().to_string());
+            comment.version = Some(captures.name("version").unwrap().as_str().to_string());
+            comment.timestamp = captures.name("time").and_then(|time| {
+                NaiveDateTime::parse_from_str(time.as_str(), "%d %b %Y %H:%M:%S")
+                    .ok()
+                    .map(|time| Utc.from_utc_datetime(&time))
+            });
+            comment.server_name = captures.name("server").map(|v| v.as_str().to_string());
+            comment.address = captures
+                .name("address")
+                .and_then(|v| v.as_str().parse().ok());
+        }
+
+        Some(comment)
+    }
+}
+
+// ------------------------------------------------------------------------------
+// Mic-E
+// ------------------------------------------------------------------------------
+
+/// Mic-E devices identified by the two character suffix of a comment starting with '`' or ''',
+/// e.g. "`...\_%" for a Yaesu FTM-400DR
+const MIC_E_DEVICES: &[(&str, &str, &str)] = &[
+    ("_ ", "Yaesu", "VX-8"),
+    ("_\"", "Yaesu", "FTM-350"),
+    ("_#", "Yaesu", "VX-8G"),
+    ("_$", "Yaesu", "FT1D"),
+    ("_%", "Yaesu", "FTM-400DR"),
+    ("_)", "Yaesu", "FTM-100D"),
+    ("_(", "Yaesu", "FT2D"),
+    ("_0", "Yaesu", "FT3D"),
+    ("_1", "Yaesu", "FTM-300D"),
+    (" X", "SainSonic", "AP510"),
+    ("(5", "Anytone", "D578UV"),
+    ("(8", "Anytone", "D878UV"),
+    ("|3", "Byonics", "TinyTrack3"),
+    ("|4", "Byonics", "TinyTrack4"),
+    (":4", "SCS GmbH & Co.", "P4dragon DR-7400"),
+    (":8", "SCS GmbH & Co.", "P4dragon DR-7800"),
+];
+
+/// Mic-E reports use '`' (current GPS data) or ''' (old GPS data) as data type identifier
+fn is_mic_e(body: &str) -> bool {
+    body.starts_with('`') || body.starts_with('\'')
+}
+
+/// Position and flags decoded from the Mic-E destination field
+struct MicEDestination {
+    latitude: f64,
+    message_bits: u8,
+    message: MicEMessage,
+    longitude_offset: bool,
+    west: bool,
+}
+
+fn parse_mic_e_destination(destination: &str) -> Option<MicEDestination> {
+    // strip the ssid if present
+    let destination = destination.split('-').next()?;
+    let characters: Vec<char> = destination.chars().collect();
+    if characters.len() != 6 {
+        return None;
+    }
+
+    let mut digits = [0u32; 6];
+    let mut message_bits = 0u8;
+    let mut has_standard_bits = false;
+    let mut has_custom_bits = false;
+    let mut flags = [false; 3];
+
+    for (i, c) in characters.iter().enumerate() {
+        // 'K', 'L' and 'Z' denote position ambiguity, treat them as 0
+        digits[i] = match c {
+            '0'..='9' => *c as u32 - '0' as u32,
+            'A'..='J' => *c as u32 - 'A' as u32,
+            'P'..='Y' => *c as u32 - 'P' as u32,
+            'K' | 'L' | 'Z' => 0,
+            _ => return None,
+        };
+
+        if i < 3 {
+            message_bits <<= 1;
+            match c {
+                'A'..='K' => {
+                    message_bits |= 1;
+                    has_custom_bits = true;
+                }
+                'P'..='Z' => {
+                    message_bits |= 1;
+                    has_standard_bits = true;
+                }
+                _ => {}
+            }
+        } else {
+            flags[i - 3] = match c {
+                '0'..='9' | 'L' => false,
+                'P'..='Z' => true,
+                _ => return None,
+            };
+        }
+    }
+
+    let message = match (message_bits, has_standard_bits, has_custom_bits) {
+        (0, _, _) => MicEMessage::Emergency,
+        (_, true, true) => MicEMessage::Unknown,
+        (bits, false, true) => MicEMessage::Custom(7 - bits),
+        (7, _, _) => MicEMessage::OffDuty,
+        (6, _, _) => MicEMessage::EnRoute,
+        (5, _, _) => MicEMessage::InService,
+        (4, _, _) => MicEMessage::Returning,
+        (3, _, _) => MicEMessage::Committed,
+        (2, _, _) => MicEMessage::Special,
+        _ => MicEMessage::Priority,
+    };
+
+    let mut latitude = to_decimal_degrees(
+        digits[0] * 10 + digits[1],
+        (digits[2] * 1000 + digits[3] * 100 + digits[4] * 10 + digits[5]) * 10,
+    );
+    if !flags[0] {
+        latitude = -latitude;
+    }
+
+    Some(MicEDestination {
+        latitude,
+        message_bits,
+        message,
+        longitude_offset: flags[1],
+        west: flags[2],
+    })
+}
+
+fn parse_mic_e(destination: &str, body: &str, reference: DateTime<Utc>) -> Option<OgnBody> {
+    let destination = parse_mic_e_destination(destination);
+    if destination.is_none() {
+        error!(
+            "invalid mic-e destination field: '{}' ignoring message",
+            body
+        );
+        return None;
+    }
+    let destination = destination.unwrap();
+
+    // the first 9 bytes contain the data type, longitude, speed, course and symbol
+    let bytes = body.as_bytes();
+    if bytes.len() < 9 || !body.is_char_boundary(9) || bytes[1..7].iter().any(|b| *b < 28) {
+        error!(
+            "mic-e information field too short: '{}' ignoring message",
+            body
+        );
+        return None;
+    }
+    let value = |i: usize| -> u32 { bytes[i] as u32 - 28 };
+
+    let mut longitude_degrees = value(1);
+    if destination.longitude_offset {
+        longitude_degrees += 100;
+    }
+    if (180..=189).contains(&longitude_degrees) {
+        longitude_degrees -= 80;
+    } else if (190..=199).contains(&longitude_degrees) {
+        longitude_degrees -= 190;
+    }
+
+    let mut longitude_minutes = value(2);
+    if longitude_minutes >= 60 {
+        longitude_minutes -= 60;
+    }
+
+    let mut longitude =
+        to_decimal_degrees(longitude_degrees, (longitude_minutes * 100 + value(3)) * 10);
+    if destination.west {
+        longitude = -longitude;
+    }
+
+    let mut ground_speed = value(4) * 10 + value(5) / 10;
+    if ground_speed >= 800 {
+        ground_speed -= 800;
+    }
+    let mut ground_track = (value(5) % 10) * 100 + value(6);
+    if ground_track >= 400 {
+        ground_track -= 400;
+    }
+
+    let comment = &body[9..];
+    let (manufacturer, device) = match parse_mic_e_device(comment) {
+        Some((manufacturer, device)) => (Some(manufacturer.to_string()), Some(device.to_string())),
+        None => (None, None),
+    };
+
+    Some(OgnBody {
+        // mic-e reports carry no timestamp
+        timestamp: reference,
+        position: Coordinate {
+            x: destination.latitude,
+            y: longitude,
+        },
+        precision_enhancement: false,
+        symbol_table: bytes[8] as char,
+        symbol_code: bytes[7] as char,
+        ground_speed: Some(Knots(ground_speed as f32)),
+        ground_turning_rate: parse_rotation_rate(comment),
+        climb_rate: parse_climb_rate(comment),
+        altitude: parse_mic_e_altitude(comment),
+        ground_track: Some(ground_track as u16),
+        gps_accuracy: parse_gps_accuracy(comment),
+        signal_strength: parse_signal_strength(comment),
+        id: parse_id(comment),
+        mic_e: Some(MicE {
+            message_bits: destination.message_bits,
+            message: destination.message,
+            manufacturer,
+            device,
+        }),
+    })
+}
+
+/// The optional mic-e altitude is encoded as three base-91 digits followed by '}' in meters
+/// relative to 10km below sea level
+fn parse_mic_e_altitude(comment: &str) -> Option<Feet> {
+    let regex = Regex::new(r"(?P<altitude>[!-{]{3})\}").unwrap();
+    let captures = regex.captures(comment)?;
+
+    let meters = captures
+        .name("altitude")
+        .unwrap()
+        .as_str()
+        .bytes()
+        .fold(0i32, |value, b| value * 91 + (b as i32 - 33))
+        - 10000;
+
+    Some(Feet::from_meters(meters as f32))
+}
+
+fn parse_mic_e_device(comment: &str) -> Option<(&'static str, &'static str)> {
+    // the suffix may end with a space (Yaesu VX-8), so only the line terminator is removed here
+    let untrimmed = comment.trim_end_matches(&['\r', '\n'][..]);
+    let comment = untrimmed.trim_end();
+
+    // Kenwood radios prefix the comment and may append a model specific suffix
+    if let Some(rest) = comment.strip_prefix('>') {
+        return match rest.chars().last() {
+            Some('=') => Some(("Kenwood", "TH-D72")),
+            Some('^') => Some(("Kenwood", "TH-D74")),
+            Some('&') => Some(("Kenwood", "TH-D75")),
+            _ => Some(("Kenwood", "TH-D7A")),
+        };
+    }
+    if let Some(rest) = comment.strip_prefix(']') {
+        return match rest.chars().last() {
+            Some('=') => Some(("Kenwood", "TM-D710")),
+            _ => Some(("Kenwood", "TM-D700")),
+        };
     }
+
+    if !comment.starts_with('`') && !comment.starts_with('\'') {
+        return None;
+    }
+
+    MIC_E_DEVICES
+        .iter()
+        .find(|(suffix, _, _)| untrimmed.len() > suffix.len() && untrimmed.ends_with(suffix))
+        .map(|(_, manufacturer, device)| (*manufacturer, *device))
 }
 
 #[cfg(test)]
@@ -283,6 +977,11 @@ mod tests {
         });
     }
 
+    /// reception time the test messages are resolved against
+    fn reference() -> DateTime<Utc> {
+        Utc.ymd(2021, 6, 1).and_hms(12, 0, 0)
+    }
+
     #[test]
     fn parse_full_message() {
         setup();
@@ -293,43 +992,84 @@ mod tests {
         // create the expected output
         let header = OgnHeader {
             sender_id: "OGN82149C".to_string(),
+            target: "OGNTRK".to_string(),
+            path: Vec::new(),
             receiver: "OxfBarton".to_string(),
             transmission_method: "qAS".to_string(),
         };
 
         let body = OgnBody {
-            timestamp: Utc::today().and_hms(13, 02, 08),
+            timestamp: Utc.ymd(2021, 6, 1).and_hms(13, 2, 8),
             position: Coordinate {
-                x: 51.4595,
-                y: 001.1150,
+                x: 51.0 + 45.953 / 60.0,
+                y: -(1.0 + 11.503 / 60.0),
             },
-            altitude: 295.0,
-            climb_rate: Some(0.0),
-            ground_speed: 0.0,
-            ground_turning_rate: Some(-4.3),
-            ground_track: 232,
+            precision_enhancement: true,
+            symbol_table: '/',
+            symbol_code: '\'',
+            altitude: Some(Feet(295)),
+            climb_rate: Some(FeetPerMinute(0.0)),
+            ground_speed: Some(Knots(0.0)),
+            ground_turning_rate: Some(HalfTurnsPerMinute(-4.3)),
+            ground_track: Some(232),
             gps_accuracy: Some("3x5".to_string()),
+            signal_strength: Some(55.0),
             id: Some("3782149C".to_string()),
+            mic_e: None,
         };
 
-        let expected = OgnTransmission {
-            header: header,
-            body: body,
-        };
+        let expected = OgnTransmission { header, body };
 
-        let parsed_position = OgnTransmission::parse(test_message).unwrap();
+        let parsed_position = OgnTransmission::parse_at(test_message, reference()).unwrap();
 
         assert_eq!(parsed_position, expected);
     }
 
     #[test]
-    fn parse_login_answer_test() {
+    fn parse_timestamp_across_midnight() {
         setup();
 
-        assert!(parse_login_answer(r"# logresp user verified, server GLIDERN2") == true);
+        let test_message = r#"OGN82149C>OGNTRK,qAS,OxfBarton:/235959h5145.95N/00111.50W'232/000/A=000295 !W33! id3782149C +000fpm"#;
+
+        let received = Utc.ymd(2021, 6, 2).and_hms(0, 0, 1);
+        let parsed = OgnTransmission::parse_at(test_message, received).unwrap();
+        assert_eq!(
+            parsed.body.timestamp,
+            Utc.ymd(2021, 6, 1).and_hms(23, 59, 59)
+        );
+
+        // DDHHMMz timestamps are accepted as well
+        let test_message =
+            r#"OGN82149C>OGNTRK,qAS,OxfBarton:/012359z5145.95N/00111.50W'232/000/A=000295"#;
+        let parsed = OgnTransmission::parse_at(test_message, received).unwrap();
+        assert_eq!(
+            parsed.body.timestamp,
+            Utc.ymd(2021, 6, 1).and_hms(23, 59, 0)
+        );
+    }
 
-        assert!(parse_login_answer(r"# logresp user unverified, server GLIDERN1") == false);
-        assert!(parse_login_answer("random string") == false);
+    #[test]
+    fn parse_login_answer_test() {
+        setup();
+
+        assert!(parse_login_answer(
+            r"# logresp user verified, server GLIDERN2"
+        ));
+
+        assert!(!parse_login_answer(
+            r"# logresp user unverified, server GLIDERN1"
+        ));
+        assert!(!parse_login_answer("random string"));
+
+        assert_eq!(
+            parse_login_server(r"# logresp user verified, server GLIDERN2"),
+            Some("GLIDERN2".to_string())
+        );
+        assert_eq!(
+            parse_login_server(r"# logresp N0CALL unverified, server GLIDERN1"),
+            Some("GLIDERN1".to_string())
+        );
+        assert_eq!(parse_login_server("random string"), None);
     }
 
     #[test]
@@ -339,6 +1079,8 @@ mod tests {
         let expected_header1 = OgnHeader {
             sender_id: "LFNW".to_string(),
             receiver: "GLIDERN5".to_string(),
+            target: "APRS".to_string(),
+            path: vec!["TCPIP*".to_string()],
             transmission_method: "qAC".to_string(),
         };
 
@@ -349,6 +1091,8 @@ mod tests {
         let expected_header2 = OgnHeader {
             sender_id: "LFNW".to_string(),
             receiver: "GLIDERN5".to_string(),
+            target: "APRS".to_string(),
+            path: vec!["TCPIP*".to_string()],
             transmission_method: "qAC".to_string(),
         };
         let ogn_message_header2 = r"LFNW>APRS,TCPIP*,qAC,GLIDERN5";
@@ -358,6 +1102,8 @@ mod tests {
         let expected_header3 = OgnHeader {
             sender_id: "FLRDDE626".to_string(),
             receiver: "EGHL".to_string(),
+            target: "APRS".to_string(),
+            path: Vec::new(),
             transmission_method: "qAS".to_string(),
         };
         let ogn_message_header3 = r"FLRDDE626>APRS,qAS,EGHL";
@@ -372,97 +1118,435 @@ mod tests {
         // Case 1
         let ogn_message_body1 = r"/074548h5111.32N/00102.04W'086/007/A=000607 !W80! id0ADDE626 -019fpm +0.0rot 5.5dB 3e -4.3kHz gps2x2";
         let expected_body1 = OgnBody {
-            timestamp: Utc::today().and_hms(07, 45, 48),
+            timestamp: Utc.ymd(2021, 6, 1).and_hms(7, 45, 48),
             position: Coordinate {
-                x: 51.1132,
-                y: 1.0204,
+                x: 51.0 + 11.328 / 60.0,
+                y: -(1.0 + 2.040 / 60.0),
             },
-            ground_track: 86,
-            ground_speed: 7.0,
-            altitude: 607.0,
+            precision_enhancement: true,
+            symbol_table: '/',
+            symbol_code: '\'',
+            ground_track: Some(86),
+            ground_speed: Some(Knots(7.0)),
+            altitude: Some(Feet(607)),
             id: Some("0ADDE626".to_string()),
-            climb_rate: Some(-19.0),
-            ground_turning_rate: Some(0.0),
+            climb_rate: Some(FeetPerMinute(-19.0)),
+            ground_turning_rate: Some(HalfTurnsPerMinute(0.0)),
+            mic_e: None,
             gps_accuracy: Some("2x2".to_string()),
+            signal_strength: Some(5.5),
         };
-        let parsed_body1 = parse_body(ogn_message_body1);
+        let parsed_body1 = parse_body(ogn_message_body1, reference());
         assert_eq!(parsed_body1.unwrap(), expected_body1);
 
         // case 2
         let ogn_message_body2 = r"/200746h5008.11N\00839.28En000/000/A=001280 !W51! id3ED0077D -019fpm +0.0rot 0.2dB 4e -6.9kHz gps2x4";
         let expected_body2 = OgnBody {
-            timestamp: Utc::today().and_hms(20, 07, 46),
+            timestamp: Utc.ymd(2021, 6, 1).and_hms(20, 7, 46),
             position: Coordinate {
-                x: 50.0811,
-                y: 8.3928,
+                x: 50.0 + 8.115 / 60.0,
+                y: 8.0 + 39.281 / 60.0,
             },
-            ground_track: 000,
-            ground_speed: 000.0,
-            altitude: 1280.0,
+            precision_enhancement: true,
+            symbol_table: '\\',
+            symbol_code: 'n',
+            ground_track: Some(000),
+            ground_speed: Some(Knots(000.0)),
+            altitude: Some(Feet(1280)),
             id: Some("3ED0077D".to_string()),
-            climb_rate: Some(-19.0),
-            ground_turning_rate: Some(0.0),
+            climb_rate: Some(FeetPerMinute(-19.0)),
+            ground_turning_rate: Some(HalfTurnsPerMinute(0.0)),
+            mic_e: None,
             gps_accuracy: Some("2x4".to_string()),
+            signal_strength: Some(0.2),
         };
-        let parsed_body2 = parse_body(ogn_message_body2);
+        let parsed_body2 = parse_body(ogn_message_body2, reference());
         assert_eq!(parsed_body2.unwrap(), expected_body2);
 
         // case 3
         let ogn_message_body3 =
             r"/162405h4925.73N/01706.72E'161/066/A=000790 !W52! id2022449E +003fpm gps5x3";
         let expected_body3 = OgnBody {
-            timestamp: Utc::today().and_hms(16, 24, 05),
+            timestamp: Utc.ymd(2021, 6, 1).and_hms(16, 24, 5),
             position: Coordinate {
-                x: 49.2573,
-                y: 17.0672,
+                x: 49.0 + 25.735 / 60.0,
+                y: 17.0 + 6.722 / 60.0,
             },
-            ground_track: 161,
-            ground_speed: 66.0,
-            altitude: 790.0,
+            precision_enhancement: true,
+            symbol_table: '/',
+            symbol_code: '\'',
+            ground_track: Some(161),
+            ground_speed: Some(Knots(66.0)),
+            altitude: Some(Feet(790)),
             id: Some("2022449E".to_string()),
-            climb_rate: Some(3.0),
+            climb_rate: Some(FeetPerMinute(3.0)),
             gps_accuracy: Some("5x3".to_string()),
+            signal_strength: None,
             ground_turning_rate: None,
+            mic_e: None,
         };
-        let parsed_body3 = parse_body(ogn_message_body3);
+        let parsed_body3 = parse_body(ogn_message_body3, reference());
         assert_eq!(parsed_body3.unwrap(), expected_body3);
 
         // case 4
         let ogn_message_body4 = r"/164425h5115.68N/00005.56Wz000/001/A=000614 !W25! id0308A689 +0fpm FNT10 22.0dB +58.8kHz 2e";
         let expected_body4 = OgnBody {
-            timestamp: Utc::today().and_hms(16, 44, 25),
+            timestamp: Utc.ymd(2021, 6, 1).and_hms(16, 44, 25),
             position: Coordinate {
-                x: 51.1568,
-                y: 00.0556,
+                x: 51.0 + 15.682 / 60.0,
+                y: -(5.565 / 60.0),
             },
-            ground_track: 000,
-            ground_speed: 1.0,
-            altitude: 614.0,
+            precision_enhancement: true,
+            symbol_table: '/',
+            symbol_code: 'z',
+            ground_track: Some(000),
+            ground_speed: Some(Knots(1.0)),
+            altitude: Some(Feet(614)),
             id: Some("0308A689".to_string()),
-            climb_rate: Some(0.0),
+            climb_rate: Some(FeetPerMinute(0.0)),
             gps_accuracy: None,
+            signal_strength: Some(22.0),
             ground_turning_rate: None,
+            mic_e: None,
         };
 
-        let parsed_body4 = parse_body(ogn_message_body4).unwrap();
+        let parsed_body4 = parse_body(ogn_message_body4, reference()).unwrap();
         assert_eq!(parsed_body4, expected_body4);
 
         let ogn_message_body5 = r"/060854h4656.38NI00723.46E&/A=001877";
         let expected_body5 = OgnBody {
-            timestamp: Utc::today().and_hms(06, 08, 54),
+            timestamp: Utc.ymd(2021, 6, 1).and_hms(6, 8, 54),
             position: Coordinate {
-                x: 46.5638,
-                y: 07.2346,
+                x: 46.0 + 56.38 / 60.0,
+                y: 7.0 + 23.46 / 60.0,
             },
+            precision_enhancement: false,
+            symbol_table: 'I',
+            symbol_code: '&',
             ground_track: None,
             ground_speed: None,
-            altitude: Some(1877),
+            altitude: Some(Feet(1877)),
             id: None,
             climb_rate: None,
             gps_accuracy: None,
+            signal_strength: None,
             ground_turning_rate: None,
+            mic_e: None,
         };
-        let parsed_body5 = parse_body(ogn_message_body5).unwrap();
+        let parsed_body5 = parse_body(ogn_message_body5, reference()).unwrap();
         assert_eq!(parsed_body5, expected_body5);
     }
+
+    #[test]
+    fn parse_rotation_rate_test() {
+        setup();
+
+        assert_eq!(
+            parse_rotation_rate("-019fpm +1.5rot"),
+            Some(HalfTurnsPerMinute(1.5))
+        );
+        assert_eq!(parse_rotation_rate("-2rot"), Some(HalfTurnsPerMinute(-2.0)));
+        // malformed values are ignored instead of panicking
+        assert_eq!(parse_rotation_rate("+1.2.3rot"), None);
+        assert_eq!(parse_rotation_rate("+.rot"), None);
+    }
+
+    #[test]
+    fn parse_mic_e_test() {
+        setup();
+
+        let message = r#"DL1ABC-9>TWRRU0,WIDE1-1,qAR,LSZH:`~<In"O'/`"9D}_%"#;
+        let parsed = OgnTransmission::parse_at(message, reference()).unwrap();
+
+        let expected_header = OgnHeader {
+            sender_id: "DL1ABC-9".to_string(),
+            target: "TWRRU0".to_string(),
+            path: vec!["WIDE1-1".to_string()],
+            receiver: "LSZH".to_string(),
+            transmission_method: "qAR".to_string(),
+        };
+        assert_eq!(parsed.header, expected_header);
+
+        let body = parsed.body;
+        assert_eq!(body.timestamp, reference());
+        assert_eq!(
+            body.position,
+            Coordinate {
+                x: 47.0 + 22.5 / 60.0,
+                y: 8.0 + 32.45 / 60.0,
+            }
+        );
+        assert_eq!((body.symbol_table, body.symbol_code), ('/', '\''));
+        assert_eq!(body.ground_speed, Some(Knots(20.0)));
+        assert_eq!(body.ground_track, Some(251));
+        assert_eq!(body.altitude, Some(Feet(1640)));
+        assert_eq!(
+            body.mic_e,
+            Some(MicE {
+                message_bits: 0b111,
+                message: MicEMessage::OffDuty,
+                manufacturer: Some("Yaesu".to_string()),
+                device: Some("FTM-400DR".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_mic_e_device_test() {
+        setup();
+
+        assert_eq!(parse_mic_e_device("`_%"), Some(("Yaesu", "FTM-400DR")));
+        assert_eq!(parse_mic_e_device("`_ "), Some(("Yaesu", "VX-8")));
+        assert_eq!(parse_mic_e_device("`_ \r\n"), Some(("Yaesu", "VX-8")));
+        assert_eq!(parse_mic_e_device(">Hello="), Some(("Kenwood", "TH-D72")));
+        assert_eq!(parse_mic_e_device("]Hello= "), Some(("Kenwood", "TM-D710")));
+        assert_eq!(parse_mic_e_device("no device"), None);
+    }
+
+    #[test]
+    fn parse_mic_e_destination_test() {
+        setup();
+
+        // south, west, longitude offset, custom message C1
+        let destination = parse_mic_e_destination("AB20PW-3").unwrap();
+        assert_eq!(destination.latitude, -(1.0 + 20.07 / 60.0));
+        assert_eq!(destination.message_bits, 0b110);
+        assert_eq!(destination.message, MicEMessage::Custom(1));
+        assert!(destination.longitude_offset);
+        assert!(destination.west);
+
+        // all message bits cleared
+        let destination = parse_mic_e_destination("012345").unwrap();
+        assert_eq!(destination.message, MicEMessage::Emergency);
+        assert!(!destination.longitude_offset);
+        assert!(!destination.west);
+
+        // mixed standard and custom bits
+        let destination = parse_mic_e_destination("PA0000").unwrap();
+        assert_eq!(destination.message, MicEMessage::Unknown);
+
+        assert!(parse_mic_e_destination("APRS").is_none());
+        assert!(parse_mic_e_destination("ABCDEF").is_none());
+    }
+
+    #[test]
+    fn parse_aprs_message_test() {
+        setup();
+
+        let message = r"OGN123456>APRS,qAS,LSZH::N0CALL-5 :Hello there{42";
+        let expected = AprsPacket::Message(
+            OgnHeader {
+                sender_id: "OGN123456".to_string(),
+                target: "APRS".to_string(),
+                path: Vec::new(),
+                receiver: "LSZH".to_string(),
+                transmission_method: "qAS".to_string(),
+            },
+            AprsMessage {
+                addressee: "N0CALL-5".to_string(),
+                content: AprsMessageContent::Text {
+                    text: "Hello there".to_string(),
+                    id: Some("42".to_string()),
+                },
+            },
+        );
+        let parsed = AprsPacket::parse(message).unwrap();
+        assert_eq!(parsed, expected);
+
+        if let AprsPacket::Message(_, message) = parsed {
+            assert!(message.is_addressed_to("n0call-5"));
+            assert!(!message.is_addressed_to("N0CALL"));
+        }
+
+        let ack = parse_message(":N0CALL   :ack42").unwrap();
+        assert_eq!(ack.addressee, "N0CALL");
+        assert_eq!(ack.content, AprsMessageContent::Ack("42".to_string()));
+
+        let rej = parse_message(":N0CALL   :rejAB1").unwrap();
+        assert_eq!(rej.content, AprsMessageContent::Rej("AB1".to_string()));
+
+        // reply-ack and messages without id
+        let reply = parse_message(":N0CALL   :Roger{MM}AA").unwrap();
+        assert_eq!(
+            reply.content,
+            AprsMessageContent::Text {
+                text: "Roger".to_string(),
+                id: Some("MM".to_string())
+            }
+        );
+        let bulletin = parse_message(":BLN1     :Field closed at 18:00").unwrap();
+        assert_eq!(bulletin.addressee, "BLN1");
+        assert_eq!(
+            bulletin.content,
+            AprsMessageContent::Text {
+                text: "Field closed at 18:00".to_string(),
+                id: None
+            }
+        );
+
+        assert!(parse_message(":N0CALL:too short").is_none());
+    }
+
+    #[test]
+    fn parse_aprs_object_test() {
+        setup();
+
+        let message =
+            r"LSTB>APRS,TCPIP*,qAC,GLIDERN2:;WINCH-1  *101530h4656.38N/00723.46E_Winch north !W52!";
+        let expected = AprsObject {
+            name: "WINCH-1".to_string(),
+            alive: true,
+            timestamp: Some(Utc.ymd(2021, 6, 1).and_hms(10, 15, 30)),
+            position: Coordinate {
+                x: 46.0 + 56.385 / 60.0,
+                y: 7.0 + 23.462 / 60.0,
+            },
+            symbol_table: '/',
+            symbol_code: '_',
+            comment: "Winch north !W52!".to_string(),
+        };
+
+        match AprsPacket::parse_at(message, reference()).unwrap() {
+            AprsPacket::Object(header, object) => {
+                assert_eq!(header.sender_id, "LSTB");
+                assert_eq!(object, expected);
+            }
+            other => panic!("expected an object, got {:?}", other),
+        }
+
+        let killed = parse_object(r";WINCH-1  _010959z4656.38N/00723.46E_", reference()).unwrap();
+        assert!(!killed.alive);
+        assert_eq!(
+            killed.timestamp,
+            Some(Utc.ymd(2021, 6, 1).and_hms(9, 59, 0))
+        );
+        assert_eq!(killed.comment, "");
+    }
+
+    #[test]
+    fn parse_aprs_item_test() {
+        setup();
+
+        let message = r"LSTB>APRS,TCPIP*,qAC,GLIDERN2:)RWY25!4656.38N\00723.46Er Runway 25";
+        let expected = AprsItem {
+            name: "RWY25".to_string(),
+            alive: true,
+            position: Coordinate {
+                x: 46.0 + 56.38 / 60.0,
+                y: 7.0 + 23.46 / 60.0,
+            },
+            symbol_table: '\\',
+            symbol_code: 'r',
+            comment: "Runway 25".to_string(),
+        };
+
+        match AprsPacket::parse(message).unwrap() {
+            AprsPacket::Item(_, item) => assert_eq!(item, expected),
+            other => panic!("expected an item, got {:?}", other),
+        }
+
+        let killed = parse_item(r")RWY25_4656.38N\00723.46Er").unwrap();
+        assert!(!killed.alive);
+        assert!(parse_item(r")AB!4656.38N\00723.46Er").is_none());
+    }
+
+    #[test]
+    fn parse_ogn_status_test() {
+        setup();
+
+        let message = r"OGN82149C>OGNTRK,qAS,OxfBarton:>130208h Pilot=John_Smith Manuf=Schempp-Hirth Model=Discus_2c ID=JS Class=18m Base=Oxford Unknown=1";
+        let expected = OgnStatusMessage::builder()
+            .pilot_name("John Smith")
+            .manufacturer("Schempp-Hirth")
+            .model("Discus 2c")
+            .competition_id("JS")
+            .competition_class("18m")
+            .base_airfield("Oxford")
+            .build()
+            .unwrap();
+
+        match AprsPacket::parse(message).unwrap() {
+            AprsPacket::Status(header, status) => {
+                assert_eq!(header.sender_id, "OGN82149C");
+                assert_eq!(status, expected);
+            }
+            other => panic!("expected a status, got {:?}", other),
+        }
+
+        // receiver status without any tracker keys
+        let message = r"LSTB>APRS,TCPIP*,qAC,GLIDERN2:>060854h v0.2.8.RPI-GPU CPU:0.6 RAM:655.1/970.5MB";
+        assert_eq!(AprsPacket::parse(message), None);
+    }
+
+    #[test]
+    fn parse_server_comment_test() {
+        setup();
+
+        let banner = ServerComment::parse("# aprsc 2.1.10-gd72a17c").unwrap();
+        assert_eq!(banner.software.as_deref(), Some("aprsc"));
+        assert_eq!(banner.version.as_deref(), Some("2.1.10-gd72a17c"));
+        assert_eq!(banner.timestamp, None);
+        assert_eq!(banner.clock_skew(reference()), None);
+
+        let keepalive = r"# aprsc 2.1.10-gd72a17c 1 Jun 2021 11:59:58 GMT GLIDERN3 1.2.3.4:14580";
+        match AprsPacket::parse(keepalive).unwrap() {
+            AprsPacket::ServerComment(comment) => {
+                assert_eq!(
+                    comment.timestamp,
+                    Some(Utc.ymd(2021, 6, 1).and_hms(11, 59, 58))
+                );
+                assert_eq!(comment.server_name.as_deref(), Some("GLIDERN3"));
+                assert_eq!(comment.address, Some(([1, 2, 3, 4], 14580).into()));
+                assert_eq!(
+                    comment.clock_skew(reference()),
+                    Some(chrono::Duration::seconds(2))
+                );
+            }
+            other => panic!("expected a server comment, got {:?}", other),
+        }
+
+        let login = ServerComment::parse("# logresp N0CALL verified, server GLIDERN1").unwrap();
+        assert_eq!(login.text, "logresp N0CALL verified, server GLIDERN1");
+        assert_eq!(login.software, None);
+        assert_eq!(OgnTransmission::parse(keepalive), None);
+        assert_eq!(
+            ServerComment::parse("LSTB>APRS,TCPIP*,qAC,GLIDERN2:>"),
+            None
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_json_test() {
+        setup();
+
+        let test_message = r#"OGN82149C>OGNTRK,qAS,OxfBarton:/130208h5145.95N/00111.50W'232/000/A=000295 !W33! id3782149C +000fpm -4.3rot FL000.00 55.0dB 0e -3.7kHz gps3x5"#;
+        let transmission = OgnTransmission::parse_at(test_message, reference()).unwrap();
+
+        let json = serde_json::to_value(&transmission).unwrap();
+        assert_eq!(json["header"]["sender_id"], "OGN82149C");
+        assert_eq!(json["body"]["id"], "3782149C");
+        assert_eq!(json["body"]["timestamp"], "2021-06-01T13:02:08Z");
+        assert_eq!(
+            json["body"]["position"],
+            serde_json::json!({
+                "latitude": 51.0 + 45.953 / 60.0,
+                "longitude": -(1.0 + 11.503 / 60.0),
+            })
+        );
+        assert_eq!(json["body"]["altitude"], 295);
+        assert_eq!(json["body"]["ground_speed"], 0.0);
+        assert_eq!(json["body"]["mic_e"], serde_json::Value::Null);
+
+        let deserialized: OgnTransmission = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized, transmission);
+
+        let packet = AprsPacket::parse_at(
+            r"OGN123456>APRS,qAS,LSZH::N0CALL-5 :Hello there{42",
+            reference(),
+        )
+        .unwrap();
+        let json = serde_json::to_string(&packet).unwrap();
+        assert_eq!(serde_json::from_str::<AprsPacket>(&json).unwrap(), packet);
+    }
 }
@@ -0,0 +1,110 @@
+use std::time::{Duration, Instant};
+
+/// Token bucket limiting the rate of uplink lines sent to the server. Every line takes one token,
+/// the tokens are refilled continuously up to the capacity, which allows short bursts while
+/// limiting the sustained rate.
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_second: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// Creates a full bucket with `capacity` tokens, refilled by `refill_per_second` tokens per
+    /// second
+    pub fn new(capacity: u32, refill_per_second: f64) -> Self {
+        Self {
+            capacity: capacity as f64,
+            tokens: capacity as f64,
+            refill_per_second: refill_per_second.max(0.0),
+            last_refill: Instant::now(),
+        }
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity as u32
+    }
+
+    pub fn refill_per_second(&self) -> f64 {
+        self.refill_per_second
+    }
+
+    pub fn try_acquire(&mut self) -> bool {
+        self.try_acquire_at(Instant::now())
+    }
+
+    /// Takes a token if available at the given time
+    pub fn try_acquire_at(&mut self, now: Instant) -> bool {
+        self.refill(now);
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Time until the next token is available, zero if a token is available right now
+    pub fn time_until_available_at(&mut self, now: Instant) -> Duration {
+        self.refill(now);
+
+        if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else if self.refill_per_second > 0.0 {
+            Duration::from_secs_f64((1.0 - self.tokens) / self.refill_per_second)
+        } else {
+            Duration::MAX
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+
+        self.tokens = (self.tokens + elapsed * self.refill_per_second).min(self.capacity);
+        self.last_refill = self.last_refill.max(now);
+    }
+}
+
+impl Default for TokenBucket {
+    /// Bursts of 10 lines, one line per second sustained
+    fn default() -> Self {
+        Self::new(10, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_bucket_test() {
+        let mut bucket = TokenBucket::new(3, 0.5);
+        let start = Instant::now();
+
+        // burst up to the capacity
+        assert!(bucket.try_acquire_at(start));
+        assert!(bucket.try_acquire_at(start));
+        assert!(bucket.try_acquire_at(start));
+        assert!(!bucket.try_acquire_at(start));
+        assert_eq!(
+            bucket.time_until_available_at(start),
+            Duration::from_secs(2)
+        );
+
+        // one token every two seconds
+        assert!(!bucket.try_acquire_at(start + Duration::from_secs(1)));
+        assert!(bucket.try_acquire_at(start + Duration::from_secs(2)));
+        assert!(!bucket.try_acquire_at(start + Duration::from_secs(2)));
+
+        // never more than the capacity
+        let later = start + Duration::from_secs(60);
+        assert_eq!(bucket.time_until_available_at(later), Duration::ZERO);
+        assert!((0..3).all(|_| bucket.try_acquire_at(later)));
+        assert!(!bucket.try_acquire_at(later));
+    }
+}
@@ -0,0 +1,145 @@
+use crate::PositionReport;
+use std::time::{Duration, Instant};
+
+/// Decides when to send the next position report of an uplink. Like OGN trackers the interval
+/// adapts to the flight state: short while turning or climbing, long while stationary on the
+/// ground. A change of the ground track by more than `track_change` degrees triggers a report
+/// immediately (corner pegging), so turns are represented in the track.
+#[derive(Debug, Clone)]
+pub struct PositionScheduler {
+    /// Interval while turning or climbing/sinking
+    pub maneuvering_interval: Duration,
+    /// Interval in straight flight
+    pub cruise_interval: Duration,
+    /// Interval while stationary
+    pub stationary_interval: Duration,
+    /// Ground speed [knots] below which the aircraft is considered stationary
+    pub stationary_speed: f32,
+    /// Turn rate [half turns per minute] above which the aircraft is considered turning
+    pub turn_rate: f32,
+    /// Vertical speed [feet per minute] above which the aircraft is considered climbing/sinking
+    pub climb_rate: f32,
+    /// Change of the ground track [degrees] since the last report that triggers a report
+    pub track_change: u16,
+    last_sent: Option<(Instant, u16)>,
+}
+
+impl PositionScheduler {
+    pub fn new() -> Self {
+        Self {
+            maneuvering_interval: Duration::from_secs(2),
+            cruise_interval: Duration::from_secs(5),
+            stationary_interval: Duration::from_secs(60),
+            stationary_speed: 2.0,
+            turn_rate: 1.0,
+            climb_rate: 200.0,
+            track_change: 30,
+            last_sent: None,
+        }
+    }
+
+    /// The interval for the flight state of the report
+    pub fn interval(&self, report: &PositionReport) -> Duration {
+        if report.ground_speed.knots() < self.stationary_speed {
+            self.stationary_interval
+        } else if report.turn_rate.half_turns_per_minute().abs() >= self.turn_rate
+            || report.climb_rate.feet_per_minute().abs() >= self.climb_rate
+        {
+            self.maneuvering_interval
+        } else {
+            self.cruise_interval
+        }
+    }
+
+    pub fn is_due(&self, report: &PositionReport) -> bool {
+        self.is_due_at(report, Instant::now())
+    }
+
+    /// Whether the report should be sent at the given time
+    pub fn is_due_at(&self, report: &PositionReport, now: Instant) -> bool {
+        let (last_time, last_track) = match self.last_sent {
+            Some(v) => v,
+            None => return true,
+        };
+
+        let elapsed = now.saturating_duration_since(last_time);
+        if elapsed >= self.interval(report) {
+            return true;
+        }
+
+        let track_change = (report.ground_track as i32 - last_track as i32).rem_euclid(360);
+        let track_change = track_change.min(360 - track_change) as u16;
+
+        // no corner pegging faster than the maneuvering interval or while stationary
+        elapsed >= self.maneuvering_interval
+            && report.ground_speed.knots() >= self.stationary_speed
+            && track_change > self.track_change
+    }
+
+    /// Records that the report has been sent at the given time
+    pub fn sent_at(&mut self, report: &PositionReport, now: Instant) {
+        self.last_sent = Some((now, report.ground_track));
+    }
+}
+
+impl Default for PositionScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aircraft::{AddressType, AircraftType, OgnId};
+    use crate::units::{FeetPerMinute, HalfTurnsPerMinute, Knots};
+
+    fn report() -> PositionReport {
+        PositionReport::new(
+            47.0,
+            7.0,
+            OgnId::new(0x123456, AddressType::Ogn, AircraftType::Glider),
+        )
+    }
+
+    #[test]
+    fn interval_test() {
+        let scheduler = PositionScheduler::new();
+
+        assert_eq!(scheduler.interval(&report()), Duration::from_secs(60));
+
+        let cruise = report().ground_speed(Knots(60.0)).ground_track(90);
+        assert_eq!(scheduler.interval(&cruise), Duration::from_secs(5));
+
+        let thermalling = cruise.clone().turn_rate(HalfTurnsPerMinute(-4.3));
+        assert_eq!(scheduler.interval(&thermalling), Duration::from_secs(2));
+
+        let climbing = cruise.climb_rate(FeetPerMinute(500.0));
+        assert_eq!(scheduler.interval(&climbing), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn is_due_test() {
+        let mut scheduler = PositionScheduler::new();
+        let start = Instant::now();
+        let cruise = report().ground_speed(Knots(60.0)).ground_track(350);
+
+        assert!(scheduler.is_due_at(&cruise, start));
+        scheduler.sent_at(&cruise, start);
+
+        assert!(!scheduler.is_due_at(&cruise, start + Duration::from_secs(4)));
+        assert!(scheduler.is_due_at(&cruise, start + Duration::from_secs(5)));
+
+        // track change across north triggers a report after the maneuvering interval
+        let turned = cruise.clone().ground_track(30);
+        assert!(!scheduler.is_due_at(&turned, start + Duration::from_secs(1)));
+        assert!(scheduler.is_due_at(&turned, start + Duration::from_secs(2)));
+        let slightly_turned = cruise.ground_track(10);
+        assert!(!scheduler.is_due_at(&slightly_turned, start + Duration::from_secs(2)));
+
+        // stationary
+        scheduler.sent_at(&report(), start);
+        assert!(!scheduler.is_due_at(&report().ground_track(180), start + Duration::from_secs(59)));
+        assert!(scheduler.is_due_at(&report(), start + Duration::from_secs(60)));
+    }
+}
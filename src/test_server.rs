@@ -0,0 +1,438 @@
+//! Local aprsc-like APRS-IS server to test clients end-to-end without network access.
+//!
+//! The server sends the welcome banner, validates `user .. pass .. vers ..` logins against the
+//! APRS-IS passcode, answers with `# logresp`, applies `#filter` commands (range `r/`, prefix `p/`
+//! and budlist `b/` filters), sends scripted traffic and keepalives and records the lines uplinked
+//! by the clients.
+
+use crate::geo::distance_km;
+use crate::parser::{AprsPacket, Parse};
+use crate::PORT;
+use chrono::Utc;
+use geocoding::Coordinate;
+use log::{debug, info};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const SOFTWARE: &str = "aprsc 2.1.10-test";
+const SERVER_NAME: &str = "TESTSRV";
+
+/// Login of a client as sent in the `user` line
+#[derive(Debug, Clone, PartialEq)]
+pub struct Login {
+    pub user_name: String,
+    pub pass_code: String,
+    pub app_name: String,
+    pub app_version: String,
+    /// Whether the pass code matches the user name
+    pub verified: bool,
+}
+
+#[derive(Debug)]
+struct Client {
+    id: usize,
+    stream: TcpStream,
+    login: Option<Login>,
+    filter: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    clients: Vec<Client>,
+    connections: usize,
+    logins: Vec<Login>,
+    filters: Vec<String>,
+    comments: Vec<String>,
+    uplinked: Vec<String>,
+}
+
+pub struct MockServer {
+    port: u16,
+    feed: PORT,
+    state: Arc<Mutex<State>>,
+    terminate: Arc<AtomicBool>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl MockServer {
+    /// Starts the server on a free local port. Like aprsc a `FULLFEED` server sends all traffic
+    /// while a `FILTER` server only sends traffic matching the filter of the client.
+    pub fn start(feed: PORT) -> Result<Self, std::io::Error> {
+        Self::start_with_keepalive(feed, Duration::from_secs(20))
+    }
+
+    /// Starts the server sending a keepalive comment to every logged in client in the given interval
+    pub fn start_with_keepalive(feed: PORT, keepalive: Duration) -> Result<Self, std::io::Error> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let port = listener.local_addr()?.port();
+        info!("mock server listening on port {}", port);
+
+        let state = Arc::new(Mutex::new(State::default()));
+        let terminate = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let state = state.clone();
+            let terminate = terminate.clone();
+            std::thread::spawn(move || accept(listener, state, terminate, keepalive))
+        };
+
+        Ok(Self {
+            port,
+            feed,
+            state,
+            terminate,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Sends the line to all logged in clients whose filter matches (on a server with filter
+    /// support)
+    pub fn send_line(&self, line: &str) {
+        let mut state = self.state.lock().unwrap();
+
+        for client in state.clients.iter_mut() {
+            if client.login.is_none() {
+                continue;
+            }
+
+            let capabilities = self.feed.capabilities();
+            let matches = match &client.filter {
+                _ if capabilities.full_feed => true,
+                Some(filter) => capabilities.filter && filter_matches(filter, line),
+                None => false,
+            };
+
+            if matches {
+                let _ = client.stream.write_all(format!("{}\r\n", line).as_bytes());
+            }
+        }
+    }
+
+    /// Closes all client connections, e.g. to test reconnects
+    pub fn disconnect_all(&self) {
+        let mut state = self.state.lock().unwrap();
+
+        for client in state.clients.drain(..) {
+            let _ = client.stream.shutdown(Shutdown::Both);
+        }
+    }
+
+    /// Number of accepted connections since the start
+    pub fn connection_count(&self) -> usize {
+        self.state.lock().unwrap().connections
+    }
+
+    pub fn logins(&self) -> Vec<Login> {
+        self.state.lock().unwrap().logins.clone()
+    }
+
+    /// Filters in the order they were applied
+    pub fn filters(&self) -> Vec<String> {
+        self.state.lock().unwrap().filters.clone()
+    }
+
+    /// Comment lines other than filters sent by the clients, e.g. keepalives
+    pub fn comments(&self) -> Vec<String> {
+        self.state.lock().unwrap().comments.clone()
+    }
+
+    /// Packets sent by logged in clients
+    pub fn uplinked_lines(&self) -> Vec<String> {
+        self.state.lock().unwrap().uplinked.clone()
+    }
+
+    /// Polls the condition until it is true or the timeout elapsed, returns the last result
+    pub fn wait_until(&self, timeout: Duration, condition: impl Fn(&MockServer) -> bool) -> bool {
+        let start = Instant::now();
+
+        while !condition(self) {
+            if start.elapsed() > timeout {
+                return false;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+
+        true
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.terminate.store(true, Ordering::Relaxed);
+        self.disconnect_all();
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn accept(
+    listener: TcpListener,
+    state: Arc<Mutex<State>>,
+    terminate: Arc<AtomicBool>,
+    keepalive: Duration,
+) {
+    let mut last_keepalive = Instant::now();
+    // aprsc ends its keepalive with the ip:port the client connected to
+    let address = listener
+        .local_addr()
+        .map(|address| address.to_string())
+        .unwrap_or_default();
+
+    while !terminate.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, address)) => {
+                debug!("mock server accepted connection from {}", address);
+                let _ = stream.set_nonblocking(false);
+
+                if let Err(err) = add_client(stream, &state) {
+                    debug!("mock server could not add client: {}", err);
+                }
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            Err(err) => debug!("mock server accept failed: {}", err),
+        }
+
+        if last_keepalive.elapsed() >= keepalive {
+            last_keepalive = Instant::now();
+
+            let line = format!(
+                "# {} {} {} {}\r\n",
+                SOFTWARE,
+                Utc::now().format("%-d %b %Y %H:%M:%S GMT"),
+                SERVER_NAME,
+                address
+            );
+            for client in state.lock().unwrap().clients.iter_mut() {
+                if client.login.is_some() {
+                    let _ = client.stream.write_all(line.as_bytes());
+                }
+            }
+        }
+    }
+}
+
+fn add_client(mut stream: TcpStream, state: &Arc<Mutex<State>>) -> Result<(), std::io::Error> {
+    stream.write_all(format!("# {}\r\n", SOFTWARE).as_bytes())?;
+    let reader = BufReader::new(stream.try_clone()?);
+
+    let id = {
+        let mut state = state.lock().unwrap();
+        state.connections += 1;
+        let id = state.connections;
+        state.clients.push(Client {
+            id,
+            stream,
+            login: None,
+            filter: None,
+        });
+        id
+    };
+
+    let state = state.clone();
+    std::thread::spawn(move || {
+        for line in reader.lines() {
+            match line {
+                Ok(line) => handle_line(id, line.trim_end(), &state),
+                Err(_) => break,
+            }
+        }
+
+        debug!("mock server connection {} closed", id);
+        state
+            .lock()
+            .unwrap()
+            .clients
+            .retain(|client| client.id != id);
+    });
+
+    Ok(())
+}
+
+fn handle_line(id: usize, line: &str, state: &Arc<Mutex<State>>) {
+    let mut state = state.lock().unwrap();
+    let state = &mut *state;
+    let client = match state.clients.iter_mut().find(|client| client.id == id) {
+        Some(v) => v,
+        None => return,
+    };
+
+    if client.login.is_none() {
+        if let Some((login, filter)) = parse_login(line) {
+            let answer = format!(
+                "# logresp {} {}, server {}\r\n",
+                login.user_name,
+                if login.verified {
+                    "verified"
+                } else {
+                    "unverified"
+                },
+                SERVER_NAME
+            );
+            let _ = client.stream.write_all(answer.as_bytes());
+
+            if let Some(filter) = filter {
+                client.filter = Some(filter.clone());
+                state.filters.push(filter);
+            }
+            client.login = Some(login.clone());
+            state.logins.push(login);
+        } else {
+            debug!("mock server ignoring line before login: {}", line);
+        }
+    } else if let Some(filter) = line.strip_prefix("#filter ") {
+        client.filter = Some(filter.trim().to_string());
+        state.filters.push(filter.trim().to_string());
+    } else if line.starts_with('#') {
+        state.comments.push(line.to_string());
+    } else {
+        state.uplinked.push(line.to_string());
+    }
+}
+
+/// Parses `user CALL pass CODE vers NAME VERSION [filter FILTER]`
+fn parse_login(line: &str) -> Option<(Login, Option<String>)> {
+    let mut words = line.split_whitespace();
+
+    if words.next()? != "user" {
+        return None;
+    }
+    let user_name = words.next()?.to_string();
+    if words.next()? != "pass" {
+        return None;
+    }
+    let pass_code = words.next()?.to_string();
+
+    let (mut app_name, mut app_version, mut filter) = (String::new(), String::new(), None);
+    while let Some(word) = words.next() {
+        match word {
+            "vers" => {
+                app_name = words.next().unwrap_or_default().to_string();
+                app_version = words.next().unwrap_or_default().to_string();
+            }
+            "filter" => filter = Some(words.by_ref().collect::<Vec<_>>().join(" ")),
+            _ => return None,
+        }
+    }
+
+    let verified = pass_code.parse::<i32>().ok() == Some(pass_code_of(&user_name) as i32);
+
+    Some((
+        Login {
+            user_name,
+            pass_code,
+            app_name,
+            app_version,
+            verified,
+        },
+        filter,
+    ))
+}
+
+/// The APRS-IS pass code of the callsign (without SSID)
+fn pass_code_of(callsign: &str) -> u16 {
+    let callsign = callsign
+        .split('-')
+        .next()
+        .unwrap_or_default()
+        .to_uppercase();
+    let mut hash: u16 = 0x73e2;
+
+    for pair in callsign.as_bytes().chunks(2) {
+        hash ^= (pair[0] as u16) << 8;
+        if let Some(&second) = pair.get(1) {
+            hash ^= second as u16;
+        }
+    }
+
+    hash & 0x7fff
+}
+
+/// Whether any term of the filter matches the packet. Supported are range `r/lat/lon/km`, prefix
+/// `p/AB/CD` and budlist `b/CALL1/CALL2` (with `*` wildcards) terms.
+fn filter_matches(filter: &str, line: &str) -> bool {
+    let sender = line.split('>').next().unwrap_or_default();
+
+    filter.split_whitespace().any(|term| {
+        let mut parts = term.split('/');
+        match parts.next() {
+            Some("r") => {
+                let values: Vec<f64> = parts.filter_map(|v| v.parse().ok()).collect();
+                match (values.as_slice(), packet_position(line)) {
+                    ([latitude, longitude, range], Some(position)) => {
+                        distance_km(
+                            position,
+                            Coordinate {
+                                x: *latitude,
+                                y: *longitude,
+                            },
+                        ) <= *range
+                    }
+                    _ => false,
+                }
+            }
+            Some("p") => parts.any(|prefix| sender.starts_with(prefix)),
+            Some("b") => parts.any(|call| match call.strip_suffix('*') {
+                Some(prefix) => sender.starts_with(prefix),
+                None => sender == call,
+            }),
+            _ => {
+                debug!("mock server ignoring unsupported filter: {}", term);
+                false
+            }
+        }
+    })
+}
+
+fn packet_position(line: &str) -> Option<Coordinate<f64>> {
+    match AprsPacket::parse(line)? {
+        AprsPacket::Position(transmission) => Some(transmission.body.position),
+        AprsPacket::Object(_, object) => Some(object.position),
+        AprsPacket::Item(_, item) => Some(item.position),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pass_code_test() {
+        assert_eq!(pass_code_of("N0CALL"), 13023);
+        assert_eq!(pass_code_of("n0call-9"), 13023);
+
+        let (login, filter) =
+            parse_login("user N0CALL pass 13023 vers ogn_client_rs 0.1.0 filter r/47/7/100")
+                .unwrap();
+        assert!(login.verified);
+        assert_eq!(login.app_name, "ogn_client_rs");
+        assert_eq!(filter, Some("r/47/7/100".to_string()));
+
+        assert!(!parse_login("user N0CALL pass -1").unwrap().0.verified);
+        assert_eq!(parse_login("usr N0CALL pass -1"), None);
+    }
+
+    #[test]
+    fn filter_test() {
+        let line = r"LSTB>APRS,TCPIP*,qAC,GLIDERN2:/060854h4656.38NI00723.46E&/A=001877";
+
+        assert!(filter_matches("r/47/7/100", line));
+        assert!(!filter_matches("r/52/-1/100", line));
+        assert!(filter_matches("r/52/-1/100 p/LS", line));
+        assert!(filter_matches("b/OGN123456/LST*", line));
+        assert!(!filter_matches("b/LST", line));
+        assert!(!filter_matches("t/p", line));
+    }
+}
@@ -0,0 +1,219 @@
+use crate::parser::Parse;
+use chrono::prelude::*;
+use chrono::Duration;
+use regex::Regex;
+
+/// Timestamp as sent in the APRS body. It only carries the time of day (and the day of month), the
+/// full date has to be reconstructed with a reference time, e.g. the reception or log time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AprsTimestamp {
+    /// `HHMMSSh` in UTC
+    HourMinuteSecond { hour: u32, minute: u32, second: u32 },
+    /// `DDHHMMz` in UTC
+    DayHourMinuteUtc { day: u32, hour: u32, minute: u32 },
+    /// `DDHHMM/` in local time, the local time zone of this machine is assumed
+    DayHourMinuteLocal { day: u32, hour: u32, minute: u32 },
+}
+
+impl Parse for AprsTimestamp {
+    type Item = Self;
+
+    fn parse(timestamp: &str) -> Option<Self> {
+        let regex = Regex::new(r"^(?P<a>\d{2})(?P<b>\d{2})(?P<c>\d{2})(?P<format>[hz/])").unwrap();
+        let captures = regex.captures(timestamp)?;
+
+        let a: u32 = captures.name("a").unwrap().as_str().parse().unwrap();
+        let b: u32 = captures.name("b").unwrap().as_str().parse().unwrap();
+        let c: u32 = captures.name("c").unwrap().as_str().parse().unwrap();
+
+        match captures.name("format").unwrap().as_str() {
+            "h" => Some(AprsTimestamp::HourMinuteSecond {
+                hour: a,
+                minute: b,
+                second: c,
+            }),
+            "z" => Some(AprsTimestamp::DayHourMinuteUtc {
+                day: a,
+                hour: b,
+                minute: c,
+            }),
+            _ => Some(AprsTimestamp::DayHourMinuteLocal {
+                day: a,
+                hour: b,
+                minute: c,
+            }),
+        }
+    }
+}
+
+impl AprsTimestamp {
+    /// Reconstructs the full timestamp by picking the date closest to the reference time, i.e. a
+    /// `235959h` timestamp received shortly after midnight is dated on the previous day.
+    pub fn resolve(&self, reference: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match *self {
+            AprsTimestamp::HourMinuteSecond {
+                hour,
+                minute,
+                second,
+            } => {
+                let time = NaiveTime::from_hms_opt(hour, minute, second)?;
+                let date = reference.naive_utc().date();
+
+                nearest(
+                    (-1..=1).map(|offset| {
+                        Utc.from_utc_datetime(&(date + Duration::days(offset)).and_time(time))
+                    }),
+                    reference,
+                )
+            }
+            AprsTimestamp::DayHourMinuteUtc { day, hour, minute } => {
+                let time = NaiveTime::from_hms_opt(hour, minute, 0)?;
+                let date = reference.naive_utc().date();
+
+                nearest(
+                    candidate_dates(date, day)
+                        .map(|date| Utc.from_utc_datetime(&date.and_time(time))),
+                    reference,
+                )
+            }
+            AprsTimestamp::DayHourMinuteLocal { day, hour, minute } => {
+                let time = NaiveTime::from_hms_opt(hour, minute, 0)?;
+                let date = reference.with_timezone(&Local).naive_local().date();
+
+                nearest(
+                    candidate_dates(date, day).filter_map(|date| {
+                        Local
+                            .from_local_datetime(&date.and_time(time))
+                            .earliest()
+                            .map(|local| local.with_timezone(&Utc))
+                    }),
+                    reference,
+                )
+            }
+        }
+    }
+}
+
+/// The given day of month in the current month (if it exists) and in the nearest earlier and later
+/// months that have it, e.g. 31 January and 31 March for a reference in February
+fn candidate_dates(date: NaiveDate, day: u32) -> impl Iterator<Item = NaiveDate> {
+    let months = date.year() * 12 + date.month0() as i32;
+    let in_month = move |offset: i32| {
+        let month = months + offset;
+        NaiveDate::from_ymd_opt(month.div_euclid(12), month.rem_euclid(12) as u32 + 1, day)
+    };
+
+    let earlier = (1..=12).find_map(|offset| in_month(-offset));
+    let later = (1..=12).find_map(in_month);
+
+    earlier.into_iter().chain(in_month(0)).chain(later)
+}
+
+fn nearest(
+    candidates: impl Iterator<Item = DateTime<Utc>>,
+    reference: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    candidates.min_by_key(|candidate| (*candidate - reference).num_seconds().abs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_timestamp_test() {
+        assert_eq!(
+            AprsTimestamp::parse("130208h"),
+            Some(AprsTimestamp::HourMinuteSecond {
+                hour: 13,
+                minute: 2,
+                second: 8
+            })
+        );
+        assert_eq!(
+            AprsTimestamp::parse("092345z4903.50N"),
+            Some(AprsTimestamp::DayHourMinuteUtc {
+                day: 9,
+                hour: 23,
+                minute: 45
+            })
+        );
+        assert_eq!(
+            AprsTimestamp::parse("092345/"),
+            Some(AprsTimestamp::DayHourMinuteLocal {
+                day: 9,
+                hour: 23,
+                minute: 45
+            })
+        );
+        assert_eq!(AprsTimestamp::parse("1302h"), None);
+        assert_eq!(AprsTimestamp::parse("130208x"), None);
+    }
+
+    #[test]
+    fn resolve_across_midnight_test() {
+        let timestamp = AprsTimestamp::parse("235958h").unwrap();
+
+        // received shortly after midnight
+        let reference = Utc.ymd(2021, 3, 1).and_hms(0, 0, 3);
+        assert_eq!(
+            timestamp.resolve(reference),
+            Some(Utc.ymd(2021, 2, 28).and_hms(23, 59, 58))
+        );
+
+        // replay of an archived log
+        let reference = Utc.ymd(2019, 7, 14).and_hms(23, 59, 59);
+        assert_eq!(
+            timestamp.resolve(reference),
+            Some(Utc.ymd(2019, 7, 14).and_hms(23, 59, 58))
+        );
+
+        // sender clock slightly ahead
+        let timestamp = AprsTimestamp::parse("000001h").unwrap();
+        assert_eq!(
+            timestamp.resolve(reference),
+            Some(Utc.ymd(2019, 7, 15).and_hms(0, 0, 1))
+        );
+
+        assert_eq!(
+            AprsTimestamp::parse("250000h").unwrap().resolve(reference),
+            None
+        );
+    }
+
+    #[test]
+    fn resolve_day_hour_minute_test() {
+        // end of the previous month
+        let timestamp = AprsTimestamp::parse("312359z").unwrap();
+        let reference = Utc.ymd(2021, 1, 1).and_hms(0, 1, 0);
+        assert_eq!(
+            timestamp.resolve(reference),
+            Some(Utc.ymd(2020, 12, 31).and_hms(23, 59, 0))
+        );
+
+        // the day does not exist in every month
+        let timestamp = AprsTimestamp::parse("301200z").unwrap();
+        let reference = Utc.ymd(2021, 3, 2).and_hms(12, 0, 0);
+        assert_eq!(
+            timestamp.resolve(reference),
+            Some(Utc.ymd(2021, 3, 30).and_hms(12, 0, 0))
+        );
+
+        // the day does not exist in the previous month, the month before is nearer
+        let timestamp = AprsTimestamp::parse("311200z").unwrap();
+        let reference = Utc.ymd(2021, 3, 1).and_hms(12, 0, 0);
+        assert_eq!(
+            timestamp.resolve(reference),
+            Some(Utc.ymd(2021, 1, 31).and_hms(12, 0, 0))
+        );
+
+        // local time round trips through the local time zone
+        let local = Local.ymd(2021, 6, 15).and_hms(8, 30, 0);
+        let timestamp = AprsTimestamp::parse("150830/").unwrap();
+        assert_eq!(
+            timestamp.resolve(local.with_timezone(&Utc)),
+            Some(local.with_timezone(&Utc))
+        );
+    }
+}
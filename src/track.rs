@@ -0,0 +1,381 @@
+//! Flight tracks assembled from the position beacons of the aircraft
+
+use crate::geo::distance_km;
+use crate::parser::OgnTransmission;
+use crate::units::{Feet, FeetPerMinute, Knots};
+use chrono::{DateTime, Utc};
+use geocoding::Coordinate;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// A position of the aircraft
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fix {
+    pub timestamp: DateTime<Utc>,
+    /// x: latitude, y: longitude [degrees]
+    #[cfg_attr(feature = "serde", serde(with = "crate::parser::position_serde"))]
+    pub position: Coordinate<f64>,
+    pub altitude: Option<Feet>,
+    pub ground_speed: Option<Knots>,
+    pub climb_rate: Option<FeetPerMinute>,
+}
+
+impl Fix {
+    fn new(transmission: &OgnTransmission) -> Self {
+        let body = &transmission.body;
+
+        Self {
+            timestamp: body.timestamp,
+            position: body.position,
+            altitude: body.altitude,
+            ground_speed: body.ground_speed,
+            climb_rate: body.climb_rate,
+        }
+    }
+}
+
+/// A fix of a track with the distance [km] and time since the start of the track
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackPoint<'a> {
+    pub fix: &'a Fix,
+    pub distance_km: f64,
+    pub duration: chrono::Duration,
+}
+
+/// Time ordered fixes of an aircraft without long gaps, a track has at least one fix
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "TrackData"))]
+pub struct Track {
+    pub sender_id: String,
+    fixes: Vec<Fix>,
+}
+
+/// Deserialized track before checking that it has fixes
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct TrackData {
+    sender_id: String,
+    fixes: Vec<Fix>,
+}
+
+#[cfg(feature = "serde")]
+impl std::convert::TryFrom<TrackData> for Track {
+    type Error = String;
+
+    fn try_from(data: TrackData) -> Result<Self, Self::Error> {
+        if data.fixes.is_empty() {
+            return Err(format!("track of {} without fixes", data.sender_id));
+        }
+
+        Ok(Self {
+            sender_id: data.sender_id,
+            fixes: data.fixes,
+        })
+    }
+}
+
+impl Track {
+    fn new(sender_id: &str, fix: Fix) -> Self {
+        Self {
+            sender_id: sender_id.to_string(),
+            fixes: vec![fix],
+        }
+    }
+
+    pub fn fixes(&self) -> &[Fix] {
+        &self.fixes
+    }
+
+    /// The fixes with the cumulated distance and the time since the first fix
+    pub fn iter(&self) -> impl Iterator<Item = TrackPoint<'_>> {
+        let start = self.fixes[0].timestamp;
+        let mut previous = self.fixes[0].position;
+        let mut distance = 0.0;
+
+        self.fixes.iter().map(move |fix| {
+            distance += distance_km(previous, fix.position);
+            previous = fix.position;
+
+            TrackPoint {
+                fix,
+                distance_km: distance,
+                duration: fix.timestamp - start,
+            }
+        })
+    }
+
+    pub fn start(&self) -> DateTime<Utc> {
+        self.fixes[0].timestamp
+    }
+
+    pub fn end(&self) -> DateTime<Utc> {
+        self.fixes[self.fixes.len() - 1].timestamp
+    }
+
+    pub fn duration(&self) -> chrono::Duration {
+        self.end() - self.start()
+    }
+
+    /// Length of the track [km]
+    pub fn distance_km(&self) -> f64 {
+        self.fixes
+            .windows(2)
+            .map(|pair| distance_km(pair[0].position, pair[1].position))
+            .sum()
+    }
+
+    fn last(&self) -> &Fix {
+        &self.fixes[self.fixes.len() - 1]
+    }
+}
+
+/// Track being assembled with the number of positions rejected since the last accepted fix
+#[derive(Debug)]
+struct ActiveTrack {
+    track: Track,
+    rejections: u32,
+}
+
+impl ActiveTrack {
+    fn new(sender_id: &str, fix: Fix) -> Self {
+        Self {
+            track: Track::new(sender_id, fix),
+            rejections: 0,
+        }
+    }
+}
+
+/// Assembles the tracks of all aircraft. Beacons older than the last fix of the aircraft (late
+/// relays) and positions that would require a speed above `max_speed` since the last fix are
+/// discarded. After `max_rejections` consecutive discarded positions the track starts anew from
+/// the latest one, so an outlier does not block the track: a single fix is replaced, a longer
+/// track is completed. A gap longer than `max_gap` completes the track and starts a new one, as
+/// does `complete_idle` for aircraft that stopped sending. Only beacons with an OGN id are
+/// considered.
+#[derive(Debug)]
+pub struct TrackBuilder {
+    pub max_speed: Knots,
+    pub max_gap: Duration,
+    pub max_rejections: u32,
+    active: HashMap<String, ActiveTrack>,
+    completed: Vec<Track>,
+}
+
+impl TrackBuilder {
+    pub fn new() -> Self {
+        Self {
+            max_speed: Knots(600.0),
+            max_gap: Duration::from_secs(300),
+            max_rejections: 3,
+            active: HashMap::new(),
+            completed: Vec::new(),
+        }
+    }
+
+    /// Adds the position to the track of the aircraft, returns whether it has been accepted
+    pub fn add(&mut self, transmission: &OgnTransmission) -> bool {
+        if transmission.body.id.is_none() {
+            return false;
+        }
+
+        let sender_id = &transmission.header.sender_id;
+        let fix = Fix::new(transmission);
+
+        let active = match self.active.get_mut(sender_id) {
+            Some(active) => active,
+            None => {
+                self.active
+                    .insert(sender_id.clone(), ActiveTrack::new(sender_id, fix));
+                return true;
+            }
+        };
+
+        let last = active.track.last();
+        if fix.timestamp <= last.timestamp {
+            return false;
+        }
+
+        let elapsed = (fix.timestamp - last.timestamp)
+            .to_std()
+            .unwrap_or_default();
+        if elapsed > self.max_gap {
+            let completed = std::mem::replace(active, ActiveTrack::new(sender_id, fix));
+            self.completed.push(completed.track);
+            return true;
+        }
+
+        let speed = Knots::from_meters_per_second(
+            (distance_km(last.position, fix.position) * 1000.0 / elapsed.as_secs_f64()) as f32,
+        );
+        if speed > self.max_speed {
+            active.rejections += 1;
+            if active.rejections < self.max_rejections {
+                return false;
+            }
+
+            // the last fix is the outlier or the aircraft is somewhere else by now
+            let previous = std::mem::replace(active, ActiveTrack::new(sender_id, fix));
+            if previous.track.fixes.len() > 1 {
+                self.completed.push(previous.track);
+            }
+            return true;
+        }
+
+        active.rejections = 0;
+        active.track.fixes.push(fix);
+        true
+    }
+
+    /// The track currently assembled for the aircraft
+    pub fn track(&self, sender_id: &str) -> Option<&Track> {
+        self.active.get(sender_id).map(|active| &active.track)
+    }
+
+    pub fn complete_idle(&mut self) {
+        self.complete_idle_at(Utc::now())
+    }
+
+    /// Completes the tracks whose last fix is more than `max_gap` before the given time, e.g. of
+    /// aircraft that landed and switched off. Call it regularly on a live feed to bound the memory.
+    pub fn complete_idle_at(&mut self, now: DateTime<Utc>) {
+        let max_gap =
+            chrono::Duration::from_std(self.max_gap).unwrap_or(chrono::Duration::max_value());
+        let idle: Vec<String> = self
+            .active
+            .iter()
+            .filter(|(_, active)| now.signed_duration_since(active.track.end()) > max_gap)
+            .map(|(sender_id, _)| sender_id.clone())
+            .collect();
+
+        for sender_id in idle {
+            if let Some(active) = self.active.remove(&sender_id) {
+                self.completed.push(active.track);
+            }
+        }
+    }
+
+    /// Removes the tracks completed by a gap so far
+    pub fn take_completed(&mut self) -> Vec<Track> {
+        std::mem::take(&mut self.completed)
+    }
+
+    /// Completes all tracks, e.g. at the end of a recorded feed
+    pub fn finish(&mut self) -> Vec<Track> {
+        let mut tracks = self.take_completed();
+        tracks.extend(self.active.drain().map(|(_, active)| active.track));
+        tracks.sort_by_key(|track| track.start());
+
+        tracks
+    }
+}
+
+impl Default for TrackBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parse;
+    use chrono::TimeZone;
+
+    fn reference() -> DateTime<Utc> {
+        Utc.ymd(2021, 6, 1).and_hms(12, 0, 0)
+    }
+
+    fn transmission(time: &str, latitude: &str) -> OgnTransmission {
+        OgnTransmission::parse_at(
+            &format!(
+                "FLRDDA5BA>APRS,qAS,LFMX:/{}h{}N/00723.46E'000/060/A=004500 !W00! id06DDA5BA",
+                time, latitude
+            ),
+            reference(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn track_test() {
+        let mut builder = TrackBuilder::new();
+
+        // northbound at 60 knots, one nautical mile per minute
+        assert!(builder.add(&transmission("110000", "4656.00")));
+        assert!(builder.add(&transmission("110100", "4657.00")));
+        // late relay and a jump of 60 nautical miles within a minute
+        assert!(!builder.add(&transmission("110030", "4656.50")));
+        assert!(!builder.add(&transmission("110200", "4757.00")));
+        assert!(builder.add(&transmission("110200", "4658.00")));
+
+        let track = builder.track("FLRDDA5BA").unwrap();
+        assert_eq!(track.fixes().len(), 3);
+        assert_eq!(track.duration(), chrono::Duration::minutes(2));
+        assert!((track.distance_km() - 3.704).abs() < 0.01);
+
+        let points: Vec<TrackPoint> = track.iter().collect();
+        assert_eq!(points[0].distance_km, 0.0);
+        assert!((points[1].distance_km - 1.852).abs() < 0.01);
+        assert_eq!(points[2].duration, chrono::Duration::minutes(2));
+    }
+
+    #[test]
+    fn gap_test() {
+        let mut builder = TrackBuilder::new();
+
+        builder.add(&transmission("110000", "4656.00"));
+        builder.add(&transmission("110100", "4657.00"));
+        // a gap of more than five minutes, the distance does not matter
+        assert!(builder.add(&transmission("112000", "4757.00")));
+
+        let completed = builder.take_completed();
+        assert_eq!(completed.len(), 1);
+        assert_eq!(completed[0].fixes().len(), 2);
+        assert!(builder.take_completed().is_empty());
+
+        builder.add(&transmission("112100", "4758.00"));
+        let tracks = builder.finish();
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(tracks[0].fixes().len(), 2);
+        assert!(builder.track("FLRDDA5BA").is_none());
+    }
+
+    #[test]
+    fn outlier_and_idle_test() {
+        let mut builder = TrackBuilder::new();
+
+        // the first position is an outlier 60 nautical miles off
+        assert!(builder.add(&transmission("110000", "4756.00")));
+        assert!(!builder.add(&transmission("110100", "4657.00")));
+        assert!(!builder.add(&transmission("110200", "4658.00")));
+        assert!(builder.add(&transmission("110300", "4659.00")));
+        assert!(builder.add(&transmission("110400", "4700.00")));
+        assert_eq!(builder.track("FLRDDA5BA").unwrap().fixes().len(), 2);
+        assert!(builder.take_completed().is_empty());
+
+        // no further beacon after landing
+        builder.complete_idle_at(Utc.ymd(2021, 6, 1).and_hms(11, 8, 0));
+        assert!(builder.track("FLRDDA5BA").is_some());
+        builder.complete_idle_at(Utc.ymd(2021, 6, 1).and_hms(11, 10, 0));
+        assert!(builder.track("FLRDDA5BA").is_none());
+        assert_eq!(builder.take_completed().len(), 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_json_test() {
+        let mut builder = TrackBuilder::new();
+        builder.add(&transmission("110000", "4656.00"));
+        builder.add(&transmission("110100", "4657.00"));
+        let track = builder.track("FLRDDA5BA").unwrap();
+
+        let json = serde_json::to_string(track).unwrap();
+        assert_eq!(&serde_json::from_str::<Track>(&json).unwrap(), track);
+
+        // an empty track would panic in start(), end() and iter()
+        let empty = serde_json::json!({"sender_id": "FLRDDA5BA", "fixes": []});
+        assert!(serde_json::from_value::<Track>(empty).is_err());
+    }
+}
@@ -0,0 +1,680 @@
+//! Line based transports the client reads from and writes to: TCP (optionally TLS) for APRS-IS
+//! servers, an in-memory transport for tests and a file transport to replay recorded feeds.
+
+use crate::connection::{Connection, Timeouts};
+use log::{debug, error, info};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, LineWriter, Write};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+pub trait Transport: Send {
+    /// Opens the transport, a connected transport is reopened
+    fn connect(&mut self) -> Result<(), std::io::Error>;
+
+    /// Connects to the next server after a failure on the current one (e.g. a failed login),
+    /// fails if there is no further server as for transports with a single endpoint
+    fn connect_next(&mut self) -> Result<(), std::io::Error> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::NotConnected,
+            "no further server to connect to",
+        ))
+    }
+
+    /// Address of the server currently connected to, if the transport has one
+    fn peer_address(&self) -> Option<SocketAddr> {
+        None
+    }
+
+    /// Reads a line including the line terminator into `line`, returns the number of bytes read
+    /// (0 at the end of the stream). A line longer than `max_length` bytes is skipped with an
+    /// `InvalidData` error.
+    fn read_line(&mut self, line: &mut Vec<u8>, max_length: usize)
+        -> Result<usize, std::io::Error>;
+
+    /// Writes the line, the `\r\n` line terminator is appended
+    fn write_line(&mut self, line: &str) -> Result<(), std::io::Error>;
+
+    fn close(&mut self) -> Result<(), std::io::Error>;
+}
+
+fn not_connected() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::NotConnected, "transport not connected")
+}
+
+fn line_too_long(length: usize, max_length: usize) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!(
+            "line of {} bytes exceeds the maximum length of {} bytes",
+            length, max_length
+        ),
+    )
+}
+
+/// Reads up to and including the next `\n` like `BufRead::read_until`, but never buffers more
+/// than `max_length` bytes: the rest of a longer line is consumed and discarded
+fn read_line_limited(
+    reader: &mut impl BufRead,
+    line: &mut Vec<u8>,
+    max_length: usize,
+) -> Result<usize, std::io::Error> {
+    let start = line.len();
+    let mut length = 0;
+
+    loop {
+        let (done, used) = {
+            let available = match reader.fill_buf() {
+                Ok(available) => available,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+
+            let (done, used) = match available.iter().position(|byte| *byte == b'\n') {
+                Some(position) => (true, position + 1),
+                None => (available.is_empty(), available.len()),
+            };
+            if length + used <= max_length {
+                line.extend_from_slice(&available[..used]);
+            }
+
+            (done, used)
+        };
+
+        reader.consume(used);
+        length += used;
+
+        if done {
+            break;
+        }
+    }
+
+    if length > max_length {
+        line.truncate(start);
+        return Err(line_too_long(length, max_length));
+    }
+
+    Ok(length)
+}
+
+/// Connection to an APRS-IS server, plain TCP or TLS. The transport tries all addresses the
+/// configured servers resolve to (IPv4 and IPv6, e.g. the rotation of `aprs.glidernet.org`) in
+/// order, starting with the last server it successfully connected to.
+#[derive(Debug)]
+pub struct TcpTransport {
+    servers: Vec<(String, u16)>,
+    timeouts: Timeouts,
+    #[cfg(feature = "tls")]
+    tls: Option<crate::connection::TlsConfig>,
+    candidates: Vec<(String, SocketAddr)>,
+    current: Option<usize>,
+    last_good: Option<(String, SocketAddr)>,
+    reader: Option<BufReader<Connection>>,
+    writer: Option<LineWriter<Connection>>,
+}
+
+impl TcpTransport {
+    pub fn new(host: &str, port: u16) -> Self {
+        Self::with_server_list(vec![(host.to_string(), port)])
+    }
+
+    /// Servers given as `host`, `host:port`, `ipv4:port` or `[ipv6]:port`, the default port is
+    /// used if the port is omitted
+    pub fn with_servers(servers: &[&str], default_port: u16) -> Self {
+        Self::with_server_list(
+            servers
+                .iter()
+                .map(|server| parse_server(server, default_port))
+                .collect(),
+        )
+    }
+
+    fn with_server_list(servers: Vec<(String, u16)>) -> Self {
+        Self {
+            servers,
+            timeouts: Timeouts::default(),
+            #[cfg(feature = "tls")]
+            tls: None,
+            candidates: Vec::new(),
+            current: None,
+            last_good: None,
+            reader: None,
+            writer: None,
+        }
+    }
+
+    /// Timeouts of the connection, the connect timeout applies to every resolved address
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Connects via TLS, see `TlsConfig` for the trusted certificates and the port of the TLS
+    /// listener
+    #[cfg(feature = "tls")]
+    pub fn tls(mut self, tls: crate::connection::TlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Host name and address of the server currently connected to
+    pub fn current_server(&self) -> Option<(&str, SocketAddr)> {
+        match (self.current, &self.reader) {
+            (Some(index), Some(_)) => {
+                Some((self.candidates[index].0.as_str(), self.candidates[index].1))
+            }
+            _ => None,
+        }
+    }
+
+    /// All addresses of all servers, the last good server first
+    fn resolve(&self) -> Vec<(String, SocketAddr)> {
+        let mut candidates: Vec<(String, SocketAddr)> = self.last_good.iter().cloned().collect();
+
+        for (host, port) in self.servers.iter() {
+            match (host.as_str(), self.effective_port(*port)).to_socket_addrs() {
+                Ok(addresses) => {
+                    for address in addresses {
+                        if !candidates.iter().any(|(_, v)| *v == address) {
+                            candidates.push((host.clone(), address));
+                        }
+                    }
+                }
+                Err(err) => error!("could not resolve {}: {}", host, err),
+            }
+        }
+
+        candidates
+    }
+
+    /// The port of the TLS listener replaces the ports of the servers
+    fn effective_port(&self, port: u16) -> u16 {
+        #[cfg(feature = "tls")]
+        if let Some(port) = self.tls.as_ref().and_then(|tls| tls.listener_port()) {
+            return port;
+        }
+
+        port
+    }
+
+    /// Connects to the first reachable candidate starting at the given index
+    fn connect_from(&mut self, start: usize) -> Result<(), std::io::Error> {
+        let mut last_error = std::io::Error::new(
+            std::io::ErrorKind::NotConnected,
+            "no further server to connect to",
+        );
+
+        for index in start..self.candidates.len() {
+            let (host, address) = &self.candidates[index];
+            info!("trying to connect to {} ({})...", host, address);
+
+            match open(
+                host,
+                address,
+                &self.timeouts,
+                #[cfg(feature = "tls")]
+                self.tls.as_ref(),
+            ) {
+                Ok(connection) => {
+                    self.writer = Some(LineWriter::new(connection.try_clone()?));
+                    self.reader = Some(BufReader::new(connection));
+                    self.current = Some(index);
+                    self.last_good = Some(self.candidates[index].clone());
+                    return Ok(());
+                }
+                Err(err) => {
+                    error!("could not connect to {} ({}): {}", host, address, err);
+                    last_error = err;
+                }
+            }
+        }
+
+        Err(last_error)
+    }
+}
+
+#[cfg_attr(not(feature = "tls"), allow(unused_variables))]
+fn open(
+    host: &str,
+    address: &SocketAddr,
+    timeouts: &Timeouts,
+    #[cfg(feature = "tls")] tls: Option<&crate::connection::TlsConfig>,
+) -> Result<Connection, std::io::Error> {
+    #[cfg(feature = "tls")]
+    if let Some(tls) = tls {
+        return Connection::connect_tls(address, host, tls, timeouts);
+    }
+
+    Connection::connect_plain(address, timeouts)
+}
+
+/// Splits `host:port` and `[ipv6]:port`, a bare ipv6 address is taken as host
+fn parse_server(server: &str, default_port: u16) -> (String, u16) {
+    if let Some(rest) = server.strip_prefix('[') {
+        if let Some((host, port)) = rest.split_once(']') {
+            let port = port.strip_prefix(':').and_then(|v| v.parse().ok());
+            return (host.to_string(), port.unwrap_or(default_port));
+        }
+    }
+
+    match server.split_once(':') {
+        Some((host, port)) if !port.contains(':') => match port.parse() {
+            Ok(port) => (host.to_string(), port),
+            Err(_) => (server.to_string(), default_port),
+        },
+        _ => (server.to_string(), default_port),
+    }
+}
+
+impl Transport for TcpTransport {
+    fn connect(&mut self) -> Result<(), std::io::Error> {
+        self.close()?;
+
+        self.current = None;
+        self.candidates = self.resolve();
+        if self.candidates.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "could not resolve any server",
+            ));
+        }
+
+        self.connect_from(0)
+    }
+
+    /// Fails over to the candidate after the current one, which is no longer considered good
+    fn connect_next(&mut self) -> Result<(), std::io::Error> {
+        let start = match self.current {
+            Some(index) => index + 1,
+            None => return self.connect(),
+        };
+
+        if self.last_good.as_ref() == self.candidates.get(start - 1) {
+            self.last_good = None;
+        }
+
+        self.close()?;
+        self.connect_from(start)
+    }
+
+    fn peer_address(&self) -> Option<SocketAddr> {
+        self.current_server().map(|(_, address)| address)
+    }
+
+    fn read_line(
+        &mut self,
+        line: &mut Vec<u8>,
+        max_length: usize,
+    ) -> Result<usize, std::io::Error> {
+        read_line_limited(
+            self.reader.as_mut().ok_or_else(not_connected)?,
+            line,
+            max_length,
+        )
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<(), std::io::Error> {
+        let writer = self.writer.as_mut().ok_or_else(not_connected)?;
+        writer.write_all(format!("{}\r\n", line).as_bytes())?;
+        writer.flush()
+    }
+
+    /// Keeps the position in the candidates, so `connect_next` continues after a closed
+    /// connection
+    fn close(&mut self) -> Result<(), std::io::Error> {
+        self.reader = None;
+        self.writer = None;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct MemoryState {
+    connected: bool,
+    /// Closed by the server side, the end of the stream follows the queued lines
+    closed: bool,
+    incoming: VecDeque<Vec<u8>>,
+    written: Vec<String>,
+}
+
+/// In-memory transport for tests. Clones share the state, so a test keeps a clone to feed lines to
+/// the client and to inspect the lines written by the client.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryTransport {
+    state: Arc<Mutex<MemoryState>>,
+}
+
+impl MemoryTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues a line (without line terminator) to be read by the client
+    pub fn push_line(&self, line: &str) {
+        self.push_bytes(line.as_bytes());
+    }
+
+    /// Queues a line of arbitrary bytes (without line terminator), e.g. invalid UTF-8
+    pub fn push_bytes(&self, line: &[u8]) {
+        let mut incoming = line.to_vec();
+        incoming.extend_from_slice(b"\r\n");
+        self.state.lock().unwrap().incoming.push_back(incoming);
+    }
+
+    /// The lines written so far without line terminator
+    pub fn written_lines(&self) -> Vec<String> {
+        self.state.lock().unwrap().written.clone()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.state.lock().unwrap().connected
+    }
+
+    /// Closes the connection from the server side, the client reads the end of the stream after
+    /// the queued lines
+    pub fn disconnect(&self) {
+        self.state.lock().unwrap().closed = true;
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn connect(&mut self) -> Result<(), std::io::Error> {
+        let mut state = self.state.lock().unwrap();
+        state.connected = true;
+        state.closed = false;
+        Ok(())
+    }
+
+    /// Reads the next queued line. An empty queue fails with `WouldBlock` like a read timeout, the
+    /// end of the stream is only reported after `disconnect`.
+    fn read_line(
+        &mut self,
+        line: &mut Vec<u8>,
+        max_length: usize,
+    ) -> Result<usize, std::io::Error> {
+        let mut state = self.state.lock().unwrap();
+        if !state.connected {
+            return Err(not_connected());
+        }
+
+        match state.incoming.pop_front() {
+            Some(incoming) if incoming.len() > max_length => {
+                Err(line_too_long(incoming.len(), max_length))
+            }
+            Some(incoming) => {
+                line.extend_from_slice(&incoming);
+                Ok(incoming.len())
+            }
+            None if state.closed => Ok(0),
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::WouldBlock,
+                "no line queued",
+            )),
+        }
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<(), std::io::Error> {
+        let mut state = self.state.lock().unwrap();
+        if !state.connected {
+            return Err(not_connected());
+        }
+
+        state.written.push(line.to_string());
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), std::io::Error> {
+        self.state.lock().unwrap().connected = false;
+        Ok(())
+    }
+}
+
+/// Replays a recorded feed from a file, written lines are appended to the output file if one is
+/// set and discarded otherwise
+#[derive(Debug)]
+pub struct FileTransport {
+    input: PathBuf,
+    output: Option<PathBuf>,
+    reader: Option<BufReader<File>>,
+    writer: Option<LineWriter<File>>,
+}
+
+impl FileTransport {
+    pub fn new(input: impl Into<PathBuf>) -> Self {
+        Self {
+            input: input.into(),
+            output: None,
+            reader: None,
+            writer: None,
+        }
+    }
+
+    pub fn output(mut self, output: impl Into<PathBuf>) -> Self {
+        self.output = Some(output.into());
+        self
+    }
+}
+
+impl Transport for FileTransport {
+    fn connect(&mut self) -> Result<(), std::io::Error> {
+        info!("replaying {}", self.input.display());
+
+        self.reader = Some(BufReader::new(File::open(&self.input)?));
+        self.writer = match &self.output {
+            Some(output) => Some(LineWriter::new(
+                std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(output)?,
+            )),
+            None => None,
+        };
+
+        Ok(())
+    }
+
+    fn read_line(
+        &mut self,
+        line: &mut Vec<u8>,
+        max_length: usize,
+    ) -> Result<usize, std::io::Error> {
+        read_line_limited(
+            self.reader.as_mut().ok_or_else(not_connected)?,
+            line,
+            max_length,
+        )
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<(), std::io::Error> {
+        if self.reader.is_none() {
+            return Err(not_connected());
+        }
+
+        match self.writer.as_mut() {
+            Some(writer) => {
+                writer.write_all(format!("{}\r\n", line).as_bytes())?;
+                writer.flush()
+            }
+            None => {
+                debug!("discarding line: {}", line);
+                Ok(())
+            }
+        }
+    }
+
+    fn close(&mut self) -> Result<(), std::io::Error> {
+        self.reader = None;
+        self.writer = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn parse_server_test() {
+        assert_eq!(
+            parse_server("aprs.glidernet.org", 14580),
+            ("aprs.glidernet.org".to_string(), 14580)
+        );
+        assert_eq!(
+            parse_server("glidern1.glidernet.org:10152", 14580),
+            ("glidern1.glidernet.org".to_string(), 10152)
+        );
+        assert_eq!(
+            parse_server("[2001:db8::1]:10152", 14580),
+            ("2001:db8::1".to_string(), 10152)
+        );
+        assert_eq!(
+            parse_server("2001:db8::1", 14580),
+            ("2001:db8::1".to_string(), 14580)
+        );
+    }
+
+    #[test]
+    fn tcp_transport_failover_test() {
+        // a port without listener
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let closed_port = closed.local_addr().unwrap().port();
+        drop(closed);
+
+        let first = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let second = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let servers = [
+            format!("127.0.0.1:{}", closed_port),
+            format!("127.0.0.1:{}", first.local_addr().unwrap().port()),
+            format!("localhost:{}", second.local_addr().unwrap().port()),
+        ];
+        let servers: Vec<&str> = servers.iter().map(|v| v.as_str()).collect();
+
+        let mut transport = TcpTransport::with_servers(&servers, 14580);
+        transport.connect().unwrap();
+        assert_eq!(transport.peer_address(), first.local_addr().ok());
+
+        transport.connect_next().unwrap();
+        assert_eq!(transport.current_server().unwrap().0, "localhost");
+        assert_eq!(
+            transport.peer_address().unwrap().port(),
+            second.local_addr().unwrap().port()
+        );
+
+        // the last good server is tried first
+        transport.connect().unwrap();
+        assert_eq!(transport.current_server().unwrap().0, "localhost");
+
+        // no server after the last one
+        while transport.connect_next().is_ok() {}
+        assert_eq!(transport.peer_address(), None);
+    }
+
+    #[test]
+    fn tcp_transport_timeout_test() {
+        // accepts the connection but never sends anything
+        let silent = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let timeouts = Timeouts {
+            read: Some(Duration::from_millis(100)),
+            ..Timeouts::default()
+        };
+
+        let mut transport =
+            TcpTransport::new("127.0.0.1", silent.local_addr().unwrap().port()).timeouts(timeouts);
+        transport.connect().unwrap();
+
+        let start = Instant::now();
+        let error = transport.read_line(&mut Vec::new(), 512).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+        ));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn read_line_limited_test() {
+        let input: &[u8] = b"first\r\nlatin \xe9\r\nmuch too long\r\nlast";
+        let mut reader = BufReader::with_capacity(4, input);
+        let mut line = Vec::new();
+
+        assert_eq!(read_line_limited(&mut reader, &mut line, 12).unwrap(), 7);
+        assert_eq!(line, b"first\r\n");
+        line.clear();
+        assert_eq!(read_line_limited(&mut reader, &mut line, 12).unwrap(), 9);
+        assert_eq!(line, b"latin \xe9\r\n");
+        line.clear();
+
+        // the long line is skipped, reading continues with the next line
+        let error = read_line_limited(&mut reader, &mut line, 12).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert!(line.is_empty());
+        assert_eq!(read_line_limited(&mut reader, &mut line, 12).unwrap(), 4);
+        assert_eq!(line, b"last");
+        assert_eq!(read_line_limited(&mut reader, &mut line, 12).unwrap(), 0);
+    }
+
+    #[test]
+    fn memory_transport_test() {
+        let mut transport = MemoryTransport::new();
+        let handle = transport.clone();
+        let mut line = Vec::new();
+
+        assert_eq!(
+            transport.read_line(&mut line, 512).unwrap_err().kind(),
+            std::io::ErrorKind::NotConnected
+        );
+
+        transport.connect().unwrap();
+        handle.push_line("# aprsc 2.1.10-gd72a17c");
+        assert_eq!(transport.read_line(&mut line, 512).unwrap(), 25);
+        assert_eq!(line, b"# aprsc 2.1.10-gd72a17c\r\n");
+        handle.push_line("# aprsc 2.1.10-gd72a17c");
+        assert_eq!(
+            transport.read_line(&mut line, 24).unwrap_err().kind(),
+            std::io::ErrorKind::InvalidData
+        );
+        assert_eq!(
+            transport.read_line(&mut line, 512).unwrap_err().kind(),
+            std::io::ErrorKind::WouldBlock
+        );
+        handle.disconnect();
+        assert_eq!(transport.read_line(&mut line, 512).unwrap(), 0);
+
+        transport.write_line("#filter r/47/7/100").unwrap();
+        assert_eq!(handle.written_lines(), vec!["#filter r/47/7/100"]);
+
+        transport.close().unwrap();
+        assert!(!handle.is_connected());
+    }
+
+    #[test]
+    fn file_transport_test() {
+        let directory = std::env::temp_dir().join(format!("ogn_transport_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let input = directory.join("feed.log");
+        let output = directory.join("sent.log");
+        std::fs::write(&input, "# aprsc 2.1.10-gd72a17c\nLSTB>APRS,TCPIP*,qAC,GLIDERN2:/060854h4656.38NI00723.46E&/A=001877\n").unwrap();
+
+        let mut transport = FileTransport::new(&input).output(&output);
+        transport.connect().unwrap();
+
+        let mut line = Vec::new();
+        transport.read_line(&mut line, 512).unwrap();
+        line.clear();
+        transport.read_line(&mut line, 512).unwrap();
+        assert!(line.starts_with(b"LSTB>APRS"));
+        assert_eq!(transport.read_line(&mut line, 512).unwrap(), 0);
+
+        transport.write_line("user N0CALL pass -1").unwrap();
+        transport.close().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&output).unwrap(),
+            "user N0CALL pass -1\r\n"
+        );
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,131 @@
+//! Physical quantities as sent in OGN messages. The APRS/OGN wire format uses aviation units
+//! (knots, feet, feet per minute), the accessors convert them to SI units.
+
+const METERS_PER_FOOT: f32 = 0.3048;
+const METERS_PER_NAUTICAL_MILE: f32 = 1852.0;
+
+/// Speed in knots
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Knots(pub f32);
+
+impl Knots {
+    pub fn from_meters_per_second(meters_per_second: f32) -> Self {
+        Knots(meters_per_second * 3600.0 / METERS_PER_NAUTICAL_MILE)
+    }
+
+    pub fn knots(&self) -> f32 {
+        self.0
+    }
+
+    pub fn meters_per_second(&self) -> f32 {
+        self.0 * METERS_PER_NAUTICAL_MILE / 3600.0
+    }
+
+    pub fn kilometers_per_hour(&self) -> f32 {
+        self.0 * METERS_PER_NAUTICAL_MILE / 1000.0
+    }
+}
+
+/// Vertical speed in feet per minute
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FeetPerMinute(pub f32);
+
+impl FeetPerMinute {
+    pub fn from_meters_per_second(meters_per_second: f32) -> Self {
+        FeetPerMinute(meters_per_second / METERS_PER_FOOT * 60.0)
+    }
+
+    pub fn feet_per_minute(&self) -> f32 {
+        self.0
+    }
+
+    pub fn meters_per_second(&self) -> f32 {
+        self.0 * METERS_PER_FOOT / 60.0
+    }
+}
+
+/// Altitude in feet, whole feet as in the APRS `/A=` field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Feet(pub i32);
+
+impl Feet {
+    /// Rounds to whole feet
+    pub fn from_meters(meters: f32) -> Self {
+        Feet((meters / METERS_PER_FOOT).round() as i32)
+    }
+
+    pub fn feet(&self) -> i32 {
+        self.0
+    }
+
+    pub fn meters(&self) -> f32 {
+        self.0 as f32 * METERS_PER_FOOT
+    }
+}
+
+/// Turn rate in half turns (180 degrees) per minute
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HalfTurnsPerMinute(pub f32);
+
+impl HalfTurnsPerMinute {
+    pub fn from_degrees_per_second(degrees_per_second: f32) -> Self {
+        HalfTurnsPerMinute(degrees_per_second / 3.0)
+    }
+
+    pub fn half_turns_per_minute(&self) -> f32 {
+        self.0
+    }
+
+    pub fn degrees_per_second(&self) -> f32 {
+        self.0 * 180.0 / 60.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(value: f32, expected: f32) {
+        assert!(
+            (value - expected).abs() < 1e-3,
+            "{} is not close to {}",
+            value,
+            expected
+        );
+    }
+
+    #[test]
+    fn speed_conversion_test() {
+        assert_close(Knots(100.0).kilometers_per_hour(), 185.2);
+        assert_close(Knots(100.0).meters_per_second(), 51.4444);
+        assert_close(Knots::from_meters_per_second(51.4444).knots(), 100.0);
+
+        assert_close(FeetPerMinute(100.0).meters_per_second(), 0.508);
+        assert_close(
+            FeetPerMinute::from_meters_per_second(0.508).feet_per_minute(),
+            100.0,
+        );
+    }
+
+    #[test]
+    fn altitude_conversion_test() {
+        assert_close(Feet(1000).meters(), 304.8);
+        assert_eq!(Feet::from_meters(500.0), Feet(1640));
+        assert!(Feet(1000) > Feet(-10));
+    }
+
+    #[test]
+    fn turn_rate_conversion_test() {
+        // a standard rate turn of 3 deg/s is one half turn per minute
+        assert_close(HalfTurnsPerMinute(1.0).degrees_per_second(), 3.0);
+        assert_close(HalfTurnsPerMinute(-4.3).degrees_per_second(), -12.9);
+        assert_close(
+            HalfTurnsPerMinute::from_degrees_per_second(6.0).half_turns_per_minute(),
+            2.0,
+        );
+    }
+}